@@ -0,0 +1 @@
+CREATE TABLE IF NOT EXISTS pglit_migrations (version TEXT PRIMARY KEY, applied_at TIMESTAMPTZ NOT NULL DEFAULT now());
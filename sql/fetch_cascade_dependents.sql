@@ -0,0 +1,3 @@
+WITH RECURSIVE target AS (SELECT c.oid FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace WHERE n.nspname = $1 AND c.relname = $2),
+dependents(oid) AS (SELECT con.conrelid FROM pg_constraint con JOIN target t ON con.confrelid = t.oid WHERE con.contype = 'f' UNION SELECT con.conrelid FROM pg_constraint con JOIN dependents d ON con.confrelid = d.oid WHERE con.contype = 'f')
+SELECT format('%I.%I', n.nspname, c.relname) FROM dependents d JOIN pg_class c ON c.oid = d.oid JOIN pg_namespace n ON n.oid = c.relnamespace WHERE d.oid NOT IN (SELECT oid FROM target) ORDER BY 1;
@@ -0,0 +1 @@
+SELECT FROM information_schema.columns WHERE table_schema = $1 AND table_name = $2 AND column_name = $3;
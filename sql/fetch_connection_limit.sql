@@ -0,0 +1 @@
+SELECT datconnlimit FROM pg_database WHERE datname = $1;
@@ -0,0 +1 @@
+SELECT pid, usename, application_name, host(client_addr) FROM pg_stat_activity WHERE datname = $1 AND pid <> pg_backend_pid() ORDER BY pid;
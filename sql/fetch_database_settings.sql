@@ -0,0 +1 @@
+SELECT pg_encoding_to_char(encoding)::text, datcollate::text, datctype::text, pg_get_userbyid(datdba)::text, datconnlimit, datistemplate, datallowconn FROM pg_database WHERE datname = $1;
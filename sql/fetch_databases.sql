@@ -0,0 +1 @@
+SELECT d.datname::text, r.rolname::text, pg_encoding_to_char(d.encoding)::text, d.datistemplate FROM pg_database d JOIN pg_roles r ON r.oid = d.datdba WHERE $1 OR (NOT d.datistemplate AND d.datname <> 'postgres') ORDER BY d.datname;
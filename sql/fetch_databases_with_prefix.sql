@@ -0,0 +1 @@
+SELECT datname::text FROM pg_database WHERE NOT datistemplate AND datname <> $2 AND left(datname, length($1)) = $1 ORDER BY datname;
@@ -0,0 +1 @@
+SELECT EXISTS(SELECT FROM pg_tables WHERE schemaname = $1 AND tablename = t.name) FROM unnest($2::text[]) WITH ORDINALITY AS t(name, position) ORDER BY t.position;
@@ -0,0 +1 @@
+SELECT FROM pg_extension WHERE extname = $1;
@@ -0,0 +1 @@
+SELECT extname::text, extversion FROM pg_extension ORDER BY extname;
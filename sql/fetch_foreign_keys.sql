@@ -0,0 +1,8 @@
+SELECT tc.constraint_name::text, array_agg(kcu.column_name::text ORDER BY kcu.ordinal_position), rkcu.table_schema::text, rkcu.table_name::text, array_agg(rkcu.column_name::text ORDER BY kcu.ordinal_position), rc.delete_rule::text
+FROM information_schema.table_constraints tc
+JOIN information_schema.key_column_usage kcu ON kcu.constraint_schema = tc.constraint_schema AND kcu.constraint_name = tc.constraint_name AND kcu.table_schema = tc.table_schema AND kcu.table_name = tc.table_name
+JOIN information_schema.referential_constraints rc ON rc.constraint_schema = tc.constraint_schema AND rc.constraint_name = tc.constraint_name
+JOIN information_schema.key_column_usage rkcu ON rkcu.constraint_schema = rc.unique_constraint_schema AND rkcu.constraint_name = rc.unique_constraint_name AND rkcu.ordinal_position = kcu.position_in_unique_constraint
+WHERE tc.constraint_type = 'FOREIGN KEY' AND tc.table_schema = $1 AND tc.table_name = $2
+GROUP BY tc.constraint_name, rkcu.table_schema, rkcu.table_name, rc.delete_rule
+ORDER BY tc.constraint_name;
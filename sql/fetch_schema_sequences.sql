@@ -0,0 +1 @@
+SELECT sequencename::text FROM pg_sequences WHERE schemaname = $1 ORDER BY sequencename;
@@ -0,0 +1 @@
+SELECT nspname FROM pg_namespace WHERE $1 OR (nspname NOT IN ('pg_catalog', 'information_schema') AND nspname NOT LIKE 'pg\_toast%' AND nspname NOT LIKE 'pg\_temp\_%') ORDER BY nspname;
@@ -0,0 +1 @@
+SELECT pid, EXTRACT(EPOCH FROM now() - query_start)::float8, query FROM pg_stat_activity WHERE state = 'active' AND pid <> pg_backend_pid() AND now() - query_start >= $1 * interval '1 second' ORDER BY 2 DESC;
@@ -0,0 +1 @@
+SELECT NULL::int4, mean_exec_time / 1000.0, query FROM $schema.pg_stat_statements WHERE mean_exec_time >= $1 * 1000.0 ORDER BY mean_exec_time DESC;
@@ -1,2 +1 @@
-SELECT FROM pg_tables WHERE schemaname = '$schema_name' AND tablename = '$table_name';
-
+SELECT FROM pg_tables WHERE schemaname = $1 AND tablename = $2;
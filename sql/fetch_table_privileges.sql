@@ -0,0 +1 @@
+SELECT DISTINCT privilege_type::text FROM information_schema.role_table_grants WHERE table_schema = $1 AND table_name = $2 AND grantee = $3 ORDER BY 1;
@@ -0,0 +1 @@
+INSERT INTO pglit_migrations (version) VALUES ($1) ON CONFLICT DO NOTHING;
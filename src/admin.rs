@@ -0,0 +1,1317 @@
+use std::{future::Future, pin::Pin};
+
+use deadpool_postgres::tokio_postgres::{
+    error::SqlState, tls::MakeTlsConnect, tls::TlsConnect, types::ToSql, Client,
+    Config as PgConfig, Error as PGError, Row, Socket,
+};
+
+use tokio::{sync::mpsc::Sender, task::JoinHandle};
+
+use crate::events::{emit, PglitEvent};
+use crate::options::{CreateDbOptions, GrantObject, GrantSpec, IdentifierMode, Owner};
+use crate::utils::errors::{ActiveConnection, ReconcileError};
+use crate::utils::{
+    catalog_name, escape_identifier, get_sql_statement, quote_identifier, quote_literal, ADMIN_DBS,
+};
+use crate::CustomError;
+
+/// A short-lived connection to the admin database (`postgres`).
+///
+/// Opening an [`AdminSession`] performs the connection handshake once, so the admin statements
+/// issued through it don't need to reconnect to the server each time.
+/// When the server has no `postgres` database, as on some stripped-down images, the session connects to `template1` instead.
+///
+/// The session is closed when it is dropped, the task driving its connection ending shortly after.
+/// Call [`AdminSession::close`] to wait until the connection is shut down.
+#[derive(Debug)]
+pub struct AdminSession {
+    client: Client,
+    admin_db: &'static str,
+    connection: JoinHandle<()>,
+}
+
+impl AdminSession {
+    /// Opens a connection to the admin database using the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`].
+    ///
+    /// The database name in the `config` will be ignored and replaced with the admin database.
+    ///
+    /// # Errors
+    ///
+    /// See [`CustomError`] for details.
+    pub async fn connect<T>(config: &PgConfig, tls: T) -> Result<AdminSession, CustomError>
+    where
+        T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+        T::Stream: Sync + Send,
+        T::TlsConnect: Sync + Send,
+        <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+    {
+        let (client, connection, admin_db) = connect_admin(config, tls).await?;
+        Ok(AdminSession {
+            client,
+            admin_db,
+            connection,
+        })
+    }
+
+    /// Closes the session and waits for the task driving its connection to end.
+    ///
+    /// Unlike dropping the session, no task outlives this call, which matters when many sessions are opened in a row.
+    pub async fn close(self) {
+        let AdminSession {
+            client, connection, ..
+        } = self;
+        // dropping the client terminates the connection, which ends the task
+        drop(client);
+        let _ = connection.await;
+    }
+
+    /// Returns the [`Client`] connected to the admin database.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Returns the name of the admin database this session is connected to, `postgres` or `template1`.
+    pub fn admin_db(&self) -> &str {
+        self.admin_db
+    }
+
+    /// Creates the `db_name` database through this session.
+    ///
+    /// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes** (").
+    /// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the `db_name` argument is empty.
+    ///
+    /// # Errors
+    ///
+    /// See [`CustomError`] for details.
+    pub async fn create_db(&self, db_name: &str) -> Result<u64, CustomError> {
+        self.execute_db_action(db_name, "CREATE", None, IdentifierMode::default(), None)
+            .await
+    }
+
+    /// Creates the `db_name` database through this session, appending the `options` to the `CREATE DATABASE` statement.
+    ///
+    /// See [`create_db_with_options`][`crate::create_db_with_options`] for details.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the `db_name` argument is empty.
+    ///
+    /// # Errors
+    ///
+    /// See [`CustomError`] for details.
+    pub async fn create_db_with_options(
+        &self,
+        db_name: &str,
+        options: &CreateDbOptions,
+    ) -> Result<u64, CustomError> {
+        self.execute_db_action(
+            db_name,
+            "CREATE",
+            Some(options),
+            options.identifier_mode,
+            None,
+        )
+        .await
+    }
+
+    pub(crate) async fn execute_db_action(
+        &self,
+        db_name: &str,
+        action: &str,
+        options: Option<&CreateDbOptions>,
+        mode: IdentifierMode,
+        events: Option<&Sender<PglitEvent>>,
+    ) -> Result<u64, CustomError> {
+        execute_db_action(&self.client, db_name, action, options, mode, events).await
+    }
+}
+
+/// Connects to the first admin database of [`ADMIN_DBS`] that exists, spawning the connection task.
+///
+/// Only a missing database (*"3D000"*) moves on to the next one, any other error is returned as is.
+async fn connect_admin<T>(
+    config: &PgConfig,
+    tls: T,
+) -> Result<(Client, JoinHandle<()>, &'static str), CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let mut config = config.clone();
+    for (i, &admin_db) in ADMIN_DBS.iter().enumerate() {
+        let _ = config.dbname(admin_db);
+        match config.connect(tls.clone()).await {
+            Ok((client, connection)) => {
+                let connection = tokio::spawn(async move {
+                    if let Err(e) = connection.await {
+                        eprintln!("connection error: {}", e);
+                    }
+                });
+                return Ok((client, connection, admin_db));
+            }
+            Err(e)
+                if i + 1 < ADMIN_DBS.len() && e.code() == Some(&SqlState::INVALID_CATALOG_NAME) =>
+            {
+                continue
+            }
+            Err(e) => return Err(CustomError::new(e)),
+        }
+    }
+    unreachable!("the last admin database always returns")
+}
+
+/// Lists the sessions connected to the `db_name` database, other than the one of the `client`.
+async fn database_connections(
+    client: &Client,
+    db_name: &str,
+    mode: IdentifierMode,
+) -> Result<Vec<ActiveConnection>, CustomError> {
+    let statement = include_str!("../sql/fetch_database_connections.sql").trim();
+    let rows = client
+        .query(statement, &[&mode.catalog_name(db_name)])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(rows
+        .iter()
+        .map(|row| ActiveConnection {
+            pid: row.get(0),
+            user: row.get(1),
+            application_name: row.get(2),
+            client_addr: row.get(3),
+        })
+        .collect())
+}
+
+/// Runs the `action` on the `db_name` database through the `client`, sending the statement to the `events` observer if any.
+pub(crate) async fn execute_db_action(
+    client: &Client,
+    db_name: &str,
+    action: &str,
+    options: Option<&CreateDbOptions>,
+    mode: IdentifierMode,
+    events: Option<&Sender<PglitEvent>>,
+) -> Result<u64, CustomError> {
+    if db_name.is_empty() {
+        panic!("The database name in the `db_name` argument should not be empty");
+    }
+    let mut db_sql = get_sql_statement(action, &mode.quote(db_name));
+    if let Some(options) = options {
+        let clauses = create_db_clauses(client, options).await?;
+        db_sql = format!("{}{};", db_sql.trim_end_matches(';'), clauses);
+    }
+    emit(events, || PglitEvent::StatementSent {
+        statement: db_sql.clone(),
+    })
+    .await;
+    let result = client.execute(db_sql.as_str(), &[]).await.map_err(|e| {
+        let mut error = CustomError::new(e);
+        // the `client` may have been left in a transaction, e.g. when it comes from a pool
+        if error.code == "25001" {
+            error.message = format!(
+                "{}, the `client` has to be outside of a transaction",
+                error.message
+            );
+        }
+        // the most common setup mistake, the admin connection works but the user lacks the rights
+        if error.code == "42501" {
+            let hint = if action == "CREATE" {
+                "the user needs the `CREATEDB` privilege"
+            } else {
+                "only the owner of the database or a superuser can drop it"
+            };
+            error.message = format!("{}, {}", error.message, hint);
+        }
+        error.statement = Some(db_sql.clone());
+        error
+    });
+    let rows = match result {
+        Err(mut error) if error.code == "55006" && action.starts_with("DROP") => {
+            // best effort, the drop error is returned even if the sessions can't be listed
+            if let Ok(connections) = database_connections(client, db_name, mode).await {
+                if !connections.is_empty() {
+                    let sessions: Vec<String> = connections
+                        .iter()
+                        .map(|c| match &c.user {
+                            Some(user) => format!("{} ({})", c.pid, user),
+                            None => c.pid.to_string(),
+                        })
+                        .collect();
+                    error.message = format!(
+                        "{}, connected sessions: {}",
+                        error.message,
+                        sessions.join(", ")
+                    );
+                }
+                error.connections = connections;
+            }
+            return Err(error);
+        }
+        result => result?,
+    };
+    if action == "CREATE" && options.map_or(false, |options| options.verify_after_create) {
+        // a catalog-only check, so it needs no CONNECT privilege on the new database
+        // and fails with an error without code if the database isn't visible to the admin connection
+        let statement = include_str!("../sql/fetch_database.sql").trim();
+        let _ = client
+            .query_one(statement, &[&mode.catalog_name(db_name)])
+            .await
+            .map_err(CustomError::new)?;
+    }
+    Ok(rows)
+}
+
+/// Renders the `options` as the clauses of a `CREATE DATABASE` statement.
+async fn create_db_clauses(
+    client: &Client,
+    options: &CreateDbOptions,
+) -> Result<String, CustomError> {
+    let mut clauses = String::new();
+    if let Some(owner) = &options.owner {
+        let session_user = if *owner == Owner::SessionUser {
+            let row = client
+                .query_one("SELECT session_user::text", &[])
+                .await
+                .map_err(CustomError::new)?;
+            row.get(0)
+        } else {
+            String::new()
+        };
+        clauses.push_str(&format!(
+            " OWNER {}",
+            owner.to_sql(&session_user, options.identifier_mode)
+        ));
+    }
+    if let Some(template) = &options.template {
+        clauses.push_str(&format!(
+            " TEMPLATE {}",
+            options.identifier_mode.quote(template)
+        ));
+    }
+    if let Some(encoding) = &options.encoding {
+        clauses.push_str(&format!(" ENCODING {}", quote_literal(encoding)));
+    }
+    if let Some(lc_collate) = &options.lc_collate {
+        clauses.push_str(&format!(" LC_COLLATE {}", quote_literal(lc_collate)));
+    }
+    if let Some(lc_ctype) = &options.lc_ctype {
+        clauses.push_str(&format!(" LC_CTYPE {}", quote_literal(lc_ctype)));
+    }
+    clauses.push_str(&alterable_clauses(options));
+    Ok(clauses)
+}
+
+/// Renders the `options` that can also be changed by an `ALTER DATABASE ... WITH` statement.
+fn alterable_clauses(options: &CreateDbOptions) -> String {
+    let mut clauses = String::new();
+    if let Some(connection_limit) = options.connection_limit {
+        clauses.push_str(&format!(" CONNECTION LIMIT {}", connection_limit));
+    }
+    if let Some(is_template) = options.is_template {
+        clauses.push_str(&format!(" IS_TEMPLATE {}", is_template));
+    }
+    if let Some(allow_connections) = options.allow_connections {
+        clauses.push_str(&format!(" ALLOW_CONNECTIONS {}", allow_connections));
+    }
+    clauses
+}
+
+/// Creates a new database through an already opened `client`, such as an [`Object`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.Object.html) of a pool connected to the admin database.
+///
+/// This avoids a connection handshake per operation when an admin connection is already available.
+/// The `client` must not be connected to the `db_name` database.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes** (").
+/// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn create_db_with_client<F, U>(client: &Client, db_name: &str, mut cb: F) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+{
+    cb(execute_db_action(
+        client,
+        db_name,
+        "CREATE",
+        None,
+        IdentifierMode::default(),
+        None,
+    )
+    .await)
+}
+
+/// Drops a database through an already opened `client`, such as an [`Object`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.Object.html) of a pool connected to the admin database.
+///
+/// This avoids a connection handshake per operation when an admin connection is already available.
+/// The `client` must not be connected to the `db_name` database.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes** (").
+/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn drop_db_with_client<F, U>(client: &Client, db_name: &str, mut cb: F) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+{
+    cb(execute_db_action(
+        client,
+        db_name,
+        "DROP",
+        None,
+        IdentifierMode::default(),
+        None,
+    )
+    .await)
+}
+
+/// Force drops a database through an already opened `client`, such as an [`Object`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.Object.html) of a pool connected to the admin database.
+///
+/// See [`forcedrop_db`][`crate::forcedrop_db`] for details about the **_Force_** option.
+/// The `client` must not be connected to the `db_name` database.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes** (").
+/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn forcedrop_db_with_client<F, U>(client: &Client, db_name: &str, mut cb: F) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+{
+    cb(execute_db_action(
+        client,
+        db_name,
+        "DROP, WITH (FORCE);",
+        None,
+        IdentifierMode::default(),
+        None,
+    )
+    .await)
+}
+
+/// Creates the `db_name` database unless it already exists, leaving the `config` untouched.
+///
+/// Returns `true` if the database was created and `false` if it already existed.
+pub(crate) async fn create_missing_db<T>(
+    config: &PgConfig,
+    db_name: &str,
+    tls: T,
+) -> Result<bool, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    crate::create_db_if_missing(&mut config.clone(), db_name, tls).await
+}
+
+/// Same as [`create_missing_db`] for the functions returning a [`tokio_postgres::Error`][`PGError`], which can't carry an error without a postgres error.
+///
+/// The create runs without a timeout nor a pool, so its errors always wrap a postgres error.
+pub(crate) async fn create_missing_pg_db<T>(
+    config: &PgConfig,
+    db_name: &str,
+    tls: T,
+) -> Result<bool, PGError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    create_missing_db(config, db_name, tls).await.map_err(|e| {
+        e.into_pg_error()
+            .expect("a create run without a timeout nor a pool fails with a postgres error")
+    })
+}
+
+/// Provisions the `db_name` database: creates it with the `options` unless it already exists, then sets its `comment`.
+///
+/// `CREATE DATABASE` can't run inside a transaction block and `COMMENT ON DATABASE` can't be combined with it in the same statement,
+/// so the statements are sent one after the other, in this order, on a single admin connection without wrapping them in a transaction.
+/// As a consequence, the database is left created if setting the comment fails, calling this function again completes the provisioning.\
+/// When `comment` is `None` the comment of the database is left unchanged.
+///
+/// Returns `true` if the database was created and `false` if it already existed.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes** (").
+/// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn provision_db<T>(
+    config: &PgConfig,
+    db_name: &str,
+    options: &CreateDbOptions,
+    comment: Option<&str>,
+    tls: T,
+) -> Result<bool, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let session = AdminSession::connect(config, tls).await?;
+    let result = provision_db_with_session(&session, db_name, options, comment).await;
+    session.close().await;
+    result
+}
+
+/// Runs the statements of [`provision_db`] through the `session`, so it can be closed whatever the outcome.
+async fn provision_db_with_session(
+    session: &AdminSession,
+    db_name: &str,
+    options: &CreateDbOptions,
+    comment: Option<&str>,
+) -> Result<bool, CustomError> {
+    let created = match session.create_db_with_options(db_name, options).await {
+        Ok(_n) => true,
+        Err(e) if e.code == "42P04" => false,
+        Err(e) => return Err(e),
+    };
+    if let Some(comment) = comment {
+        let statement = format!(
+            "COMMENT ON DATABASE {} IS {};",
+            options.identifier_mode.quote(db_name),
+            quote_literal(comment)
+        );
+        session
+            .client()
+            .batch_execute(statement.as_str())
+            .await
+            .map_err(CustomError::new)?;
+    }
+    Ok(created)
+}
+
+/// Drops every database whose name starts with `prefix`, such as the databases left behind by interrupted test runs.
+///
+/// The `prefix` is compared with the raw names stored in `pg_database`, it is never quoted or case-folded.
+/// The admin database and the template databases are never dropped.\
+/// If `force` is set to true the databases are dropped with the **_Force_** option, see [`forcedrop_db`][`crate::forcedrop_db`] for details.
+///
+/// Returns the names of the dropped databases, in alphabetical order. The databases are dropped one by one, so if a drop fails the previous ones stay dropped.
+///
+/// # Panics
+///
+/// This function will panic if the `prefix` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn drop_databases_with_prefix<T>(
+    config: &PgConfig,
+    prefix: &str,
+    force: bool,
+    tls: T,
+) -> Result<Vec<String>, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    if prefix.is_empty() {
+        panic!("the `prefix` argument should not be empty");
+    }
+    let session = AdminSession::connect(config, tls).await?;
+    let result = drop_databases_with_prefix_with_session(&session, prefix, force).await;
+    session.close().await;
+    result
+}
+
+/// Runs the statements of [`drop_databases_with_prefix`] through the `session`, so it can be closed whatever the outcome.
+async fn drop_databases_with_prefix_with_session(
+    session: &AdminSession,
+    prefix: &str,
+    force: bool,
+) -> Result<Vec<String>, CustomError> {
+    let client = session.client();
+    let statement = include_str!("../sql/fetch_databases_with_prefix.sql").trim();
+    let rows = client
+        .query(statement, &[&prefix, &session.admin_db()])
+        .await
+        .map_err(CustomError::new)?;
+
+    let mut dropped = Vec::with_capacity(rows.len());
+    for row in rows {
+        let db_name: String = row.get(0);
+        let mut statement = format!("DROP DATABASE {}", escape_identifier(&db_name));
+        if force {
+            statement.push_str(" WITH (FORCE)");
+        }
+        let _ = client
+            .execute(statement.as_str(), &[])
+            .await
+            .map_err(CustomError::new)?;
+        dropped.push(db_name);
+    }
+    Ok(dropped)
+}
+
+/// Sets how many concurrent connections can be made to the `db_name` database by running `ALTER DATABASE db_name CONNECTION LIMIT limit`.
+///
+/// A `limit` of `-1` removes the limit. Superusers and background workers are not subject to it.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes** (").
+/// To use a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn set_connection_limit<T, F, U>(
+    config: &PgConfig,
+    db_name: &str,
+    limit: i32,
+    tls: T,
+    mut cb: F,
+) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    if db_name.is_empty() {
+        panic!("The database name in the `db_name` argument should not be empty");
+    }
+    let session = match AdminSession::connect(config, tls).await {
+        Ok(session) => session,
+        Err(e) => return cb(Err(e)),
+    };
+    let statement = format!(
+        "ALTER DATABASE {} CONNECTION LIMIT {};",
+        quote_identifier(db_name),
+        limit
+    );
+    let result = session
+        .client()
+        .execute(statement.as_str(), &[])
+        .await
+        .map_err(CustomError::new);
+    session.close().await;
+    cb(result)
+}
+
+/// Renames a database by running `ALTER DATABASE old_name RENAME TO new_name` on the admin database,
+/// such as swapping a freshly built `db_new` database into place as `db`.
+///
+/// Note that by default the `old_name` and `new_name` arguments shouldn't be enclosed in **double quotes** (").
+/// To rename a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `old_name` or the `new_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// A database can't be renamed while there are sessions connected to it, in which case *"55006", "database is being accessed by other users"* is returned.
+/// *"42P04"* is returned if a database named `new_name` already exists.
+pub async fn rename_db<T, F, U>(
+    config: &PgConfig,
+    old_name: &str,
+    new_name: &str,
+    tls: T,
+    mut cb: F,
+) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    if old_name.is_empty() {
+        panic!("The database name in the `old_name` argument should not be empty");
+    }
+    if new_name.is_empty() {
+        panic!("The database name in the `new_name` argument should not be empty");
+    }
+    let session = match AdminSession::connect(config, tls).await {
+        Ok(session) => session,
+        Err(e) => return cb(Err(e)),
+    };
+    let statement = format!(
+        "ALTER DATABASE {} RENAME TO {};",
+        quote_identifier(old_name),
+        quote_identifier(new_name)
+    );
+    let result = session
+        .client()
+        .execute(statement.as_str(), &[])
+        .await
+        .map_err(CustomError::new);
+    session.close().await;
+    cb(result)
+}
+
+/// Returns the connection limit of the `db_name` database, `-1` meaning no limit.
+///
+/// The name is matched the way [`set_connection_limit`] writes it: folded to lower case by default,
+/// or as is when the **`quotes`** feature is enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// An error without code is returned if the `db_name` database doesn't exist.
+pub async fn connection_limit<T>(
+    config: &PgConfig,
+    db_name: &str,
+    tls: T,
+) -> Result<i32, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    if db_name.is_empty() {
+        panic!("The database name in the `db_name` argument should not be empty");
+    }
+    let session = AdminSession::connect(config, tls).await?;
+    let statement = include_str!("../sql/fetch_connection_limit.sql").trim();
+    let result = session
+        .client()
+        .query_one(statement, &[&catalog_name(db_name)])
+        .await
+        .map(|row| row.get(0))
+        .map_err(CustomError::new);
+    session.close().await;
+    result
+}
+
+/// Checks if the `db_name` database exists, by looking it up in `pg_database` through a connection to the admin database.
+///
+/// The name is matched the way [`create_db`][`crate::create_db`] stores it: folded to lower case by default,
+/// or as is without the **double quotes** (") when the **`quotes`** feature is enabled.
+///
+/// Returns `false` if the database doesn't exist, and also if the admin connection or the query fails.
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::{config::Config,NoTls};
+/// use pglit::database_exists;
+///
+/// async fn check_db() {
+///     let mut config = Config::new();
+///     config.user("testuser");
+///     config.password("secretPassword");
+///     let db_exists = database_exists(&mut config, "testdb", NoTls).await;
+///     println!("testdb exists: {}", db_exists);
+/// }
+/// ```
+pub async fn database_exists<T>(config: &mut PgConfig, db_name: &str, tls: T) -> bool
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    if db_name.is_empty() {
+        panic!("The database name in the `db_name` argument should not be empty");
+    }
+    let session = match AdminSession::connect(config, tls).await {
+        Ok(session) => session,
+        Err(_e) => return false,
+    };
+    let statement = include_str!("../sql/fetch_database.sql").trim();
+    let exists = session
+        .client()
+        .query_opt(statement, &[&catalog_name(db_name)])
+        .await
+        .map_or(false, |row| row.is_some());
+    session.close().await;
+    exists
+}
+
+/// A database of the server, as returned by [`list_databases`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseInfo {
+    /// Name of the database, as stored in `pg_database`.
+    pub name: String,
+    /// Name of the role owning the database.
+    pub owner: String,
+    /// Character set encoding of the database, such as `UTF8`.
+    pub encoding: String,
+    /// Whether the database can be cloned by any user with the `CREATEDB` privilege.
+    pub is_template: bool,
+}
+
+/// Lists the databases of the server through the admin database, sorted by name.
+///
+/// The system databases, which are `postgres` and the template databases such as `template0` and `template1`,
+/// are left out unless `include_system` is `true`.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn list_databases<T>(
+    config: &PgConfig,
+    include_system: bool,
+    tls: T,
+) -> Result<Vec<DatabaseInfo>, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let statement = include_str!("../sql/fetch_databases.sql").trim();
+    let rows = run_admin_query(config, statement, &[&include_system], tls).await?;
+    Ok(rows
+        .iter()
+        .map(|row| DatabaseInfo {
+            name: row.get(0),
+            owner: row.get(1),
+            encoding: row.get(2),
+            is_template: row.get(3),
+        })
+        .collect())
+}
+
+/// Checks whether the user of the `config` is allowed to create databases, because it has the `CREATEDB` attribute or is a superuser.
+///
+/// This is a pre-flight check for provisioning tools, so that a missing privilege can be reported up front rather than as a
+/// *"42501", "permission denied to create database"* error from [`create_db`][`crate::create_db`].
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn can_create_databases<T>(config: &PgConfig, tls: T) -> Result<bool, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let session = AdminSession::connect(config, tls).await?;
+    let statement = include_str!("../sql/fetch_createdb_privilege.sql").trim();
+    let result = session
+        .client()
+        .query_one(statement, &[])
+        .await
+        .map(|row| row.get(0))
+        .map_err(CustomError::new);
+    session.close().await;
+    result
+}
+
+/// Terminates the server process (backend) identified by `pid` by running `SELECT pg_terminate_backend($1)`.
+///
+/// This is meant to kill a single runaway session, the `pid` of a session can be read from the `pid` column of `pg_stat_activity`.
+///
+/// Returns `true` if the backend was signaled and `false` if `pid` isn't a PostgreSQL backend, in which case the server only emits a warning.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// The user has to be a superuser, a member of the role owning the backend or a member of `pg_signal_backend`, otherwise *"42501"* is returned.
+pub async fn terminate_backend<T>(config: &PgConfig, pid: i32, tls: T) -> Result<bool, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let session = AdminSession::connect(config, tls).await?;
+    let result = session
+        .client()
+        .query_one("SELECT pg_terminate_backend($1)", &[&pid])
+        .await
+        .map(|row| row.get(0))
+        .map_err(CustomError::new);
+    session.close().await;
+    result
+}
+
+/// Creates the database named in the `config` unless it already exists.
+///
+/// This is the pool-agnostic counterpart of [`deadpool_create_db`][`crate::deadpool_create_db`]: call it before building a pool
+/// with another pooling crate (such as `bb8` with its own `PostgresConnectionManager`) to benefit from the same
+/// *"42P04", "Attempting to create a duplicate database."* handling.
+///
+/// Note that by default the database name shouldn't be enclosed in **double quotes** (").
+/// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `config` has no database name or an empty one.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn ensure_database<T>(config: &PgConfig, tls: T) -> Result<(), CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let db_name = match config.get_dbname() {
+        Some(db_name) if !db_name.is_empty() => db_name,
+        _ => panic!("The database name in the `config` argument should not be empty"),
+    };
+    create_missing_db(config, db_name, tls)
+        .await
+        .map(|_created| ())
+}
+
+/// Makes the `db_name` database match the `options`, creating it if it doesn't exist.
+///
+/// If the database already exists, the settings that can be altered are updated to match the `options`:
+/// the owner, the connection limit, `IS_TEMPLATE` and `ALLOW_CONNECTIONS`.
+/// The settings that are fixed at creation (the encoding, `LC_COLLATE` and `LC_CTYPE`) are compared with the existing database instead,
+/// and a [`ReconcileError::Mismatch`] is returned if they differ. The settings left to `None` are not reconciled.
+///
+/// Returns `true` if the database was created and `false` if it already existed.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes** (").
+/// To use a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`ReconcileError`] for details.
+pub async fn reconcile_db<T>(
+    config: &PgConfig,
+    db_name: &str,
+    options: &CreateDbOptions,
+    tls: T,
+) -> Result<bool, ReconcileError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    if db_name.is_empty() {
+        panic!("The database name in the `db_name` argument should not be empty");
+    }
+    let session = AdminSession::connect(config, tls).await?;
+    let result = reconcile_db_with_session(&session, db_name, options).await;
+    session.close().await;
+    result
+}
+
+/// Runs the statements of [`reconcile_db`] through the `session`, so it can be closed whatever the outcome.
+async fn reconcile_db_with_session(
+    session: &AdminSession,
+    db_name: &str,
+    options: &CreateDbOptions,
+) -> Result<bool, ReconcileError> {
+    let client = session.client();
+
+    let statement = include_str!("../sql/fetch_database_settings.sql").trim();
+    let rows = client
+        .query(statement, &[&options.identifier_mode.catalog_name(db_name)])
+        .await
+        .map_err(CustomError::new)?;
+    let row = match rows.first() {
+        Some(row) => row,
+        None => {
+            let _ = session.create_db_with_options(db_name, options).await?;
+            return Ok(true);
+        }
+    };
+
+    let fixed_settings = [
+        ("encoding", &options.encoding),
+        ("lc_collate", &options.lc_collate),
+        ("lc_ctype", &options.lc_ctype),
+    ];
+    for (index, (setting, requested)) in fixed_settings.iter().enumerate() {
+        let existing: String = row.get(index);
+        match requested {
+            Some(requested) if !requested.eq_ignore_ascii_case(&existing) => {
+                return Err(ReconcileError::Mismatch {
+                    setting,
+                    existing,
+                    requested: requested.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let db_name = options.identifier_mode.quote(db_name);
+    let mut statement = String::new();
+    if let Some(owner) = &options.owner {
+        statement.push_str(&format!(
+            "ALTER DATABASE {} OWNER TO {};",
+            db_name,
+            owner.to_alter_sql(options.identifier_mode)
+        ));
+    }
+    let clauses = alterable_clauses(options);
+    if !clauses.is_empty() {
+        statement.push_str(&format!("ALTER DATABASE {} WITH{};", db_name, clauses));
+    }
+    if !statement.is_empty() {
+        client
+            .batch_execute(statement.as_str())
+            .await
+            .map_err(CustomError::new)?;
+    }
+    Ok(false)
+}
+
+/// Ensures an application role exists with a password and has access to the `db_name` database.
+///
+/// This function uses a single admin connection to:
+/// - create the `role_name` role with the `LOGIN` attribute and the `password`, or update the password if the role already exists.
+/// - grant `ALL PRIVILEGES` (`CONNECT`, `CREATE` and `TEMPORARY`) on the `db_name` database to the role.
+///
+/// Each step is idempotent, so it is safe to call this function again with the same arguments.
+///
+/// Note that by default the `db_name` and `role_name` arguments shouldn't be enclosed in **double quotes** (").
+/// To use names enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` or the `role_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn ensure_app_role<T>(
+    config: &PgConfig,
+    db_name: &str,
+    role_name: &str,
+    password: &str,
+    tls: T,
+) -> Result<(), CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    if db_name.is_empty() {
+        panic!("The database name in the `db_name` argument should not be empty");
+    }
+    if role_name.is_empty() {
+        panic!("the `role_name` argument should not be empty");
+    }
+    let session = AdminSession::connect(config, tls).await?;
+    let result = ensure_app_role_with_client(session.client(), db_name, role_name, password).await;
+    session.close().await;
+    result
+}
+
+/// Runs the statements of [`ensure_app_role`] on the admin `client`, so its session can be closed whatever the outcome.
+async fn ensure_app_role_with_client(
+    client: &Client,
+    db_name: &str,
+    role_name: &str,
+    password: &str,
+) -> Result<(), CustomError> {
+    let role_exists = !client
+        .query(
+            include_str!("../sql/fetch_role.sql").trim(),
+            &[&catalog_name(role_name)],
+        )
+        .await
+        .map_err(CustomError::new)?
+        .is_empty();
+    let action = if role_exists { "ALTER" } else { "CREATE" };
+    let role = quote_identifier(role_name);
+    let statement = format!(
+        "{} ROLE {} WITH LOGIN PASSWORD {}; GRANT ALL PRIVILEGES ON DATABASE {} TO {};",
+        action,
+        role,
+        quote_literal(password),
+        quote_identifier(db_name),
+        role
+    );
+    client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new)
+}
+
+/// Grants privileges by running the `GRANT` statement described by the `spec`, such as `GRANT CONNECT ON DATABASE db_name TO role_name`.
+///
+/// Obtain a [`Result<(), CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the name of the object or the grantee of the `spec` is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// A grantee that doesn't exist is reported with the *"42704"* code, and an object that doesn't exist with the code of its type,
+/// such as *"3D000"* for a database, *"3F000"* for a schema or *"42P01"* for a table.
+pub async fn grant_privileges<F, U>(client: &Client, spec: &GrantSpec, mut cb: F) -> U
+where
+    F: FnMut(Result<(), CustomError>) -> U,
+{
+    spec.check_names();
+    cb(client
+        .batch_execute(spec.to_sql(false).as_str())
+        .await
+        .map_err(CustomError::new))
+}
+
+/// Revokes privileges by running the `REVOKE` statement described by the `spec`, the counterpart of [`grant_privileges`].
+///
+/// Obtain a [`Result<(), CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the name of the object or the grantee of the `spec` is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn revoke_privileges<F, U>(client: &Client, spec: &GrantSpec, mut cb: F) -> U
+where
+    F: FnMut(Result<(), CustomError>) -> U,
+{
+    spec.check_names();
+    cb(client
+        .batch_execute(spec.to_sql(true).as_str())
+        .await
+        .map_err(CustomError::new))
+}
+
+/// Grants `ALL PRIVILEGES` (`CONNECT`, `CREATE` and `TEMPORARY`) on the `db_name` database to the `role_name` role through the admin database.
+///
+/// Note that by default the `db_name` and `role_name` arguments shouldn't be enclosed in **double quotes** (").
+/// To use names enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<(), CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` or the `role_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn grant_all_on_database<T, F, U>(
+    config: &PgConfig,
+    db_name: &str,
+    role_name: &str,
+    tls: T,
+    mut cb: F,
+) -> U
+where
+    F: FnMut(Result<(), CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    if db_name.is_empty() {
+        panic!("The database name in the `db_name` argument should not be empty");
+    }
+    if role_name.is_empty() {
+        panic!("the `role_name` argument should not be empty");
+    }
+    let session = match AdminSession::connect(config, tls).await {
+        Ok(session) => session,
+        Err(e) => return cb(Err(e)),
+    };
+    let spec = GrantSpec {
+        privileges: vec![],
+        object: GrantObject::Database(db_name.to_string()),
+        grantee: role_name.to_string(),
+    };
+    let output = grant_privileges(session.client(), &spec, cb).await;
+    session.close().await;
+    output
+}
+
+/// Runs a statement against the admin database and returns the resulting rows.
+///
+/// This is the lower-level primitive for admin statements that return data, such as catalog queries on `pg_database`.
+/// The `params` are passed to the `statement` as bind parameters (`$1`, `$2`, ...).
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::{config::Config,NoTls};
+/// use pglit::run_admin_query;
+///
+/// async fn fetch_db() {
+///     let mut config = Config::new();
+///     config.user("testuser");
+///     config.password("secretPassword");
+///
+///     let rows = run_admin_query(
+///         &config,
+///         "SELECT datname FROM pg_database WHERE datname = $1",
+///         &[&"testdb"],
+///         NoTls,
+///     )
+///     .await;
+/// }
+/// ```
+///
+pub async fn run_admin_query<T>(
+    config: &PgConfig,
+    statement: &str,
+    params: &[&(dyn ToSql + Sync)],
+    tls: T,
+) -> Result<Vec<Row>, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let session = AdminSession::connect(config, tls).await?;
+    let result = session
+        .client()
+        .query(statement, params)
+        .await
+        .map_err(CustomError::new);
+    session.close().await;
+    result
+}
+
+/// Connects to the admin database, runs the `f` closure against the admin [`Client`] and then closes the connection.
+///
+/// This allows running several admin statements, such as creating a role, creating a database and granting privileges,
+/// on a single connection. Like [`with_client`][`crate::with_client`], the connection task is awaited until the connection is shut down
+/// once `f` completes, so no connection task outlives this function.
+///
+/// The database name in the `config` will be ignored and replaced with the admin database.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details. Only the errors raised while connecting are returned here, the output of `f` is returned as is.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::{config::Config,NoTls};
+/// use pglit::with_admin_client;
+///
+/// async fn provision() {
+///     let mut config = Config::new();
+///     config.user("testuser");
+///     config.password("secretPassword");
+///     let result = with_admin_client(&config, NoTls, |client| {
+///         Box::pin(async move {
+///             client.batch_execute("CREATE ROLE app LOGIN").await?;
+///             client.batch_execute("CREATE DATABASE appdb OWNER app").await
+///         })
+///     })
+///     .await;
+/// }
+/// ```
+///
+pub async fn with_admin_client<T, F, R>(config: &PgConfig, tls: T, f: F) -> Result<R, CustomError>
+where
+    F: for<'c> FnOnce(&'c Client) -> Pin<Box<dyn Future<Output = R> + Send + 'c>>,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let (client, connection, _) = connect_admin(config, tls).await?;
+    let output = f(&client).await;
+    // dropping the client terminates the connection, which ends the task
+    drop(client);
+    let _ = connection.await;
+    Ok(output)
+}
+
+/// Forces a write-ahead log checkpoint by running `CHECKPOINT` on the admin database.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// `CHECKPOINT` requires a superuser (or a member of `pg_checkpointer` since `PostgreSQL 15`), otherwise *"42501"* is returned.
+pub async fn checkpoint<T>(config: &PgConfig, tls: T) -> Result<(), CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    run_admin_query(config, "CHECKPOINT", &[], tls)
+        .await
+        .map(|_rows| ())
+}
+
+/// Signals the server to reload its configuration files by running `SELECT pg_reload_conf()` on the admin database.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// By default `pg_reload_conf` can only be executed by a superuser, otherwise *"42501"* is returned.
+pub async fn reload_conf<T>(config: &PgConfig, tls: T) -> Result<(), CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    run_admin_query(config, "SELECT pg_reload_conf()", &[], tls)
+        .await
+        .map(|_rows| ())
+}
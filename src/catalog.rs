@@ -0,0 +1,336 @@
+use std::time::Duration;
+
+use deadpool_postgres::tokio_postgres::{
+    error::SqlState, tls::MakeTlsConnect, tls::TlsConnect, Client, Config as PgConfig, Row, Socket,
+};
+
+use crate::table::analyze_table;
+use crate::utils::{escape_identifier, schema_or_public};
+use crate::CustomError;
+
+/// Returns the planner's estimate of the number of rows in a table.
+///
+/// The estimate is read from `pg_class.reltuples` so it doesn't scan the table, which makes it suitable for sanity-checking very large tables.
+/// Note that it is only an **estimate**, refreshed by `VACUUM`, `ANALYZE` and a few DDL commands, and it is `-1` for a table that has never been analyzed.\
+/// If `analyze` is set to true, `ANALYZE` is run on the table first to refresh the estimate.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` argument is empty.
+///
+/// # Errors
+///
+/// Returns an error if the table doesn't exist, see [`CustomError`] for details.
+pub async fn table_row_estimate(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+    analyze: bool,
+) -> Result<i64, CustomError> {
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
+    }
+    let schema_name = schema_or_public(schema_name);
+    if analyze {
+        analyze_table(client, schema_name, table_name).await?;
+    }
+    let statement = include_str!("../sql/table_row_estimate.sql").trim();
+    let row = client
+        .query_one(statement, &[&schema_name, &table_name])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(row.get(0))
+}
+
+/// Details of a foreign key constraint, as returned by [`foreign_keys`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKeyInfo {
+    /// Name of the constraint.
+    pub constraint_name: String,
+    /// Columns of the table that reference the other table, in the order of the constraint.
+    pub columns: Vec<String>,
+    /// Schema of the referenced table.
+    pub referenced_schema: String,
+    /// Name of the referenced table.
+    pub referenced_table: String,
+    /// Referenced columns, matching the order of `columns`.
+    pub referenced_columns: Vec<String>,
+    /// The `ON DELETE` action: `NO ACTION`, `RESTRICT`, `CASCADE`, `SET NULL` or `SET DEFAULT`.
+    pub on_delete: String,
+}
+
+/// Lists the foreign key constraints defined on a table using the `information_schema` constraint views.
+///
+/// This makes it possible to detect the dependencies of a table before altering or dropping it.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn foreign_keys(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+) -> Result<Vec<ForeignKeyInfo>, CustomError> {
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
+    }
+    let schema_name = schema_or_public(schema_name);
+    let statement = include_str!("../sql/fetch_foreign_keys.sql").trim();
+    let rows = client
+        .query(statement, &[&schema_name, &table_name])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(rows
+        .iter()
+        .map(|row| ForeignKeyInfo {
+            constraint_name: row.get(0),
+            columns: row.get(1),
+            referenced_schema: row.get(2),
+            referenced_table: row.get(3),
+            referenced_columns: row.get(4),
+            on_delete: row.get(5),
+        })
+        .collect())
+}
+
+/// Lists the tables a `CASCADE` on a table would reach, following the foreign keys that reference it, directly or through other referencing tables.
+///
+/// These are the tables `TRUNCATE ... CASCADE` would empty along with the table, while `DROP TABLE ... CASCADE` drops the foreign key constraints of these tables.
+/// The names are fully qualified as `schema.table`, each part being quoted when needed, and sorted.
+/// The table itself isn't listed, even if it references itself, and an empty list is returned if the table doesn't exist.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn cascade_dependents(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+) -> Result<Vec<String>, CustomError> {
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
+    }
+    let schema_name = schema_or_public(schema_name);
+    let statement = include_str!("../sql/fetch_cascade_dependents.sql").trim();
+    let rows = client
+        .query(statement, &[&schema_name, &table_name])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Details of an index, as returned by [`list_indexes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexInfo {
+    /// Name of the index.
+    pub name: String,
+    /// Whether the index enforces uniqueness.
+    pub is_unique: bool,
+    /// Whether the index backs the primary key of the table.
+    pub is_primary: bool,
+    /// Key columns of the index in order, an expression being returned as its definition such as `lower(email)`.
+    pub columns: Vec<String>,
+}
+
+/// Lists the indexes defined on a table from the `pg_index` catalog, sorted by name.
+///
+/// This makes it possible to know which indexes exist before altering a table.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn list_indexes(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+) -> Result<Vec<IndexInfo>, CustomError> {
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
+    }
+    let schema_name = schema_or_public(schema_name);
+    let statement = include_str!("../sql/fetch_indexes.sql").trim();
+    let rows = client
+        .query(statement, &[&schema_name, &table_name])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(rows
+        .iter()
+        .map(|row| IndexInfo {
+            name: row.get(0),
+            is_unique: row.get(1),
+            is_primary: row.get(2),
+            columns: row.get(3),
+        })
+        .collect())
+}
+
+/// An extension installed in the database, as returned by [`list_extensions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionInfo {
+    /// Name of the extension.
+    pub name: String,
+    /// Installed version of the extension.
+    pub version: String,
+}
+
+/// Lists the extensions installed in the database the `client` is connected to, sorted by name.
+///
+/// This makes it possible to verify on startup that the required extensions are present and recent enough.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn list_extensions(client: &Client) -> Result<Vec<ExtensionInfo>, CustomError> {
+    let statement = include_str!("../sql/fetch_extensions.sql").trim();
+    let rows = client
+        .query(statement, &[])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(rows
+        .iter()
+        .map(|row| ExtensionInfo {
+            name: row.get(0),
+            version: row.get(1),
+        })
+        .collect())
+}
+
+/// A slow query, as returned by [`slow_queries`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryStat {
+    /// Process ID of the backend running the query, `None` for the statistics of `pg_stat_statements`.
+    pub pid: Option<i32>,
+    /// How long the query has been running, or its mean execution time for `pg_stat_statements`.
+    pub duration: Duration,
+    /// Text of the query.
+    pub query: String,
+}
+
+/// Lists the queries that ran for at least `min_duration`, the slowest first.
+///
+/// If the `pg_stat_statements` extension is installed in the database, the statements whose **mean** execution time is at least `min_duration` are returned (`PostgreSQL 13` or later).\
+/// Otherwise, or if the extension isn't loaded through `shared_preload_libraries`, this function falls back to the queries currently **running** in `pg_stat_activity` for at least `min_duration`.
+///
+/// Note that the text of the queries run by other users is only visible to superusers and members of `pg_read_all_stats`.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn slow_queries(
+    client: &Client,
+    min_duration: Duration,
+) -> Result<Vec<QueryStat>, CustomError> {
+    let min_secs = min_duration.as_secs_f64();
+    let statement = include_str!("../sql/fetch_extension_schema.sql").trim();
+    let extension_schema = client
+        .query(statement, &[&"pg_stat_statements"])
+        .await
+        .map_err(CustomError::new)?;
+
+    if let Some(row) = extension_schema.first() {
+        let statement = include_str!("../sql/fetch_slow_statements.sql")
+            .trim()
+            .replace("$schema", &escape_identifier(row.get(0)));
+        match client.query(statement.as_str(), &[&min_secs]).await {
+            Ok(rows) => return Ok(rows.iter().map(query_stat).collect()),
+            // the extension is installed but not loaded
+            Err(e) if e.code() == Some(&SqlState::OBJECT_NOT_IN_PREREQUISITE_STATE) => {}
+            Err(e) => return Err(CustomError::new(e)),
+        }
+    }
+    let statement = include_str!("../sql/fetch_slow_activity.sql").trim();
+    let rows = client
+        .query(statement, &[&min_secs])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(rows.iter().map(query_stat).collect())
+}
+
+fn query_stat(row: &Row) -> QueryStat {
+    QueryStat {
+        pid: row.get(0),
+        duration: Duration::from_secs_f64(row.get::<_, f64>(1).max(0.0)),
+        query: row.get::<_, Option<String>>(2).unwrap_or_default(),
+    }
+}
+
+/// A summary of the objects of a database, as returned by [`database_object_summary`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbSummary {
+    /// Number of schemas, excluding the system schemas (`pg_catalog`, `information_schema`, ...).
+    pub schemas: i64,
+    /// Number of tables in these schemas, partitioned tables included.
+    pub tables: i64,
+    /// Number of sessions connected to the database, excluding the one opened by [`database_object_summary`].
+    pub connections: i64,
+}
+
+/// Counts the schemas, the tables and the sessions of the `db_name` database through a transient connection to it.
+///
+/// This is meant to show what is about to be destroyed before a destructive operation such as [`forcedrop_db`][`crate::forcedrop_db`].\
+/// Unlike [`connect`][`crate::connect`], the database isn't created if it doesn't exist.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes** (").
+/// To use a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// *"3D000"* is returned if the `db_name` database doesn't exist.
+pub async fn database_object_summary<T>(
+    config: &PgConfig,
+    db_name: &str,
+    tls: T,
+) -> Result<DbSummary, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    if db_name.is_empty() {
+        panic!("The database name in the `db_name` argument should not be empty");
+    }
+    let mut config = config.clone();
+    let _ = config.dbname(db_name);
+    let (client, connection) = config.connect(tls).await.map_err(CustomError::new)?;
+    drop(tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    }));
+    let statement = include_str!("../sql/fetch_database_summary.sql").trim();
+    let row = client
+        .query_one(statement, &[])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(DbSummary {
+        schemas: row.get(0),
+        tables: row.get(1),
+        connections: row.get(2),
+    })
+}
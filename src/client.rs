@@ -0,0 +1,299 @@
+use std::fmt;
+
+use deadpool_postgres::tokio_postgres::{
+    tls::MakeTlsConnect, tls::TlsConnect, Client, Config as PgConfig, Connection,
+    Error as TokioError, Socket,
+};
+use deadpool_postgres::{ConfigError, CreatePoolError, Manager, Pool, Runtime};
+use tokio::sync::Mutex;
+
+use crate::admin::{create_missing_db, AdminSession};
+use crate::managed::connect_existing;
+use crate::options::{Confirm, IdentifierMode};
+use crate::utils::RedactedConfig;
+use crate::CustomError;
+
+/// Holds the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`] and the TLS connector used to reach the server.
+///
+/// The [`Debug`][`fmt::Debug`] output of [`PgLit`] never contains the password, it is redacted to `"****"`.
+///
+/// The create and drop methods share an [`AdminSession`] opened on first use, so a sequence of admin operations
+/// only pays for one connection handshake. The session is reopened if its connection was closed.
+///
+/// The session holds a connection to the admin database until [`PgLit::close_admin`] is called or the [`PgLit`] is dropped,
+/// so a long-lived [`PgLit`] occupies a connection slot of the server in the meantime and the admin database can't be dropped.
+/// The calls using the session run one at a time.
+pub struct PgLit<T> {
+    config: PgConfig,
+    tls: T,
+    admin: Mutex<Option<AdminSession>>,
+}
+
+impl PgLit<Unset> {
+    /// Starts a [`PgLitBuilder`], which requires both the config and the TLS connector to be set before a [`PgLit`] can be built.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tokio_postgres::{config::Config,NoTls};
+    /// use pglit::PgLit;
+    ///
+    /// async fn create_the_db() {
+    ///     let mut config = Config::new();
+    ///     config.user("testuser");
+    ///     config.password("secretPassword");
+    ///     let pglit = PgLit::builder().config(config).tls(NoTls).build();
+    ///     pglit.create_db("testdb", |res| println!("{:?}", res)).await;
+    /// }
+    /// ```
+    pub fn builder() -> PgLitBuilder<Unset, Unset> {
+        PgLitBuilder {
+            config: Unset,
+            tls: Unset,
+        }
+    }
+}
+
+impl<T> PgLit<T> {
+    /// Creates a new [`PgLit`] from a [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`] and a TLS connector.
+    pub fn new(config: PgConfig, tls: T) -> PgLit<T> {
+        PgLit {
+            config,
+            tls,
+            admin: Mutex::new(None),
+        }
+    }
+
+    /// Returns the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`].
+    pub fn config(&self) -> &PgConfig {
+        &self.config
+    }
+
+    /// Returns the TLS connector.
+    pub fn tls(&self) -> &T {
+        &self.tls
+    }
+
+    /// Closes the shared [`AdminSession`], if one is open, and waits for its connection to end.
+    ///
+    /// The next create or drop call opens a new session, call this after a batch of admin operations to release the connection.
+    pub async fn close_admin(&self) {
+        if let Some(session) = self.admin.lock().await.take() {
+            session.close().await;
+        }
+    }
+}
+
+impl<T> PgLit<T>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    /// Creates the database named in the config if it is missing and returns a [`Pool`] connected to it.
+    ///
+    /// The pool reuses the config and the TLS connector held by this [`PgLit`].
+    /// Like [`deadpool_create_db`][`crate::deadpool_create_db`] it handles the *"42P04", "Attempting to create a duplicate database."* postgres error,
+    /// and the returned boolean is `true` if the database was created or `false` if it already existed.
+    ///
+    /// # Important
+    /// Note that by default the `dbname` in the config shouldn't be enclosed in **double quotes**.
+    /// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+    ///
+    /// # Errors
+    ///
+    /// See [`CreatePoolError`] for details.
+    pub async fn into_pool(
+        self,
+        runtime: Option<Runtime>,
+    ) -> Result<(Pool, bool), CreatePoolError> {
+        let db_name = match self.config.get_dbname() {
+            None => return Err(CreatePoolError::Config(ConfigError::DbnameEmpty)),
+            Some("") => return Err(CreatePoolError::Config(ConfigError::DbnameMissing)),
+            Some(db_name) => db_name.to_string(),
+        };
+        let created = create_missing_db(&self.config, &db_name, self.tls.clone())
+            .await
+            .map_err(CustomError::into_create_pool_error)?;
+
+        let mut builder = Pool::builder(Manager::new(self.config, self.tls));
+        if let Some(runtime) = runtime {
+            builder = builder.runtime(runtime);
+        }
+        let pool = builder.build().map_err(CreatePoolError::Build)?;
+        Ok((pool, created))
+    }
+
+    /// Creates the `db_name` database, see [`create_db`][`crate::create_db`] for details.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the `db_name` argument is empty.
+    pub async fn create_db<F, U>(&self, db_name: &str, mut cb: F) -> U
+    where
+        F: FnMut(Result<u64, CustomError>) -> U,
+    {
+        cb(self.execute_db_action(db_name, "CREATE").await)
+    }
+
+    /// Drops the `db_name` database, see [`drop_db`][`crate::drop_db`] for details.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the `db_name` argument is empty.
+    pub async fn drop_db<F, U>(&self, db_name: &str, mut cb: F) -> U
+    where
+        F: FnMut(Result<u64, CustomError>) -> U,
+    {
+        cb(self.execute_db_action(db_name, "DROP").await)
+    }
+
+    /// Force drops the `db_name` database, see [`forcedrop_db`][`crate::forcedrop_db`] for details.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the `db_name` argument is empty.
+    pub async fn forcedrop_db<F, U>(&self, db_name: &str, _confirm: Confirm, mut cb: F) -> U
+    where
+        F: FnMut(Result<u64, CustomError>) -> U,
+    {
+        cb(self.execute_db_action(db_name, "DROP, WITH (FORCE);").await)
+    }
+
+    /// Checks if a table exists in the `db_name` database, see [`try_table_exists`][`crate::try_table_exists`] for details.
+    ///
+    /// A short-lived connection to the `db_name` database is opened for the check, the database isn't created if it is missing.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the `table_name` argument is empty.
+    ///
+    /// # Errors
+    ///
+    /// See [`CustomError`] for details.
+    pub async fn table_exists(
+        &self,
+        db_name: &str,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Result<bool, CustomError> {
+        let client = connect_existing(&self.config, db_name, self.tls.clone()).await?;
+        crate::try_table_exists(&client, schema_name, table_name).await
+    }
+
+    /// Creates schemas in the `db_name` database, see [`create_schemas`][`crate::create_schemas`] for details.
+    ///
+    /// A short-lived connection to the `db_name` database is opened, so the `search_path` is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the `schemas_names` argument is empty.
+    ///
+    /// # Errors
+    ///
+    /// See [`CustomError`] for details.
+    pub async fn create_schemas(
+        &self,
+        db_name: &str,
+        schemas_names: &[&str],
+    ) -> Result<(), CustomError> {
+        let client = connect_existing(&self.config, db_name, self.tls.clone()).await?;
+        crate::create_schemas(&client, schemas_names, false, |res| res).await
+    }
+
+    /// Runs the `action` through the shared admin session, opening it on first use or once its connection was closed.
+    async fn execute_db_action(&self, db_name: &str, action: &str) -> Result<u64, CustomError> {
+        if db_name.is_empty() {
+            panic!("The database name in the `db_name` argument should not be empty");
+        }
+        let mut admin = self.admin.lock().await;
+        let session = match admin.take() {
+            Some(session) if !session.client().is_closed() => session,
+            _ => AdminSession::connect(&self.config, self.tls.clone()).await?,
+        };
+        let result = session
+            .execute_db_action(db_name, action, None, IdentifierMode::default(), None)
+            .await;
+        *admin = Some(session);
+        result
+    }
+
+    /// Connects to the `db_name` database, creating it if needed, see [`connect`][`crate::connect`] for details.
+    ///
+    /// # Errors
+    ///
+    /// See [`tokio_postgres::error`][`deadpool_postgres::tokio_postgres::error`] for details.
+    pub async fn connect(
+        &self,
+        db_name: &str,
+    ) -> Result<(Client, Connection<Socket, T::Stream>), TokioError> {
+        crate::connect(self.config.clone(), db_name, self.tls.clone()).await
+    }
+}
+
+impl<T> fmt::Debug for PgLit<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PgLit")
+            .field("config", &RedactedConfig(&self.config))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Marks a stage of a [`PgLitBuilder`] that hasn't been set yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Unset;
+
+/// Builds a [`PgLit`] in typed stages, see [`PgLit::builder`].
+///
+/// [`build`][`PgLitBuilder::build`] is only available once both the config and a TLS connector satisfying the
+/// [`MakeTlsConnect`] bounds have been set, so a missing or unsuitable connector is reported where it is passed to [`tls`][`PgLitBuilder::tls`].
+pub struct PgLitBuilder<C, T> {
+    config: C,
+    tls: T,
+}
+
+impl<T> PgLitBuilder<Unset, T> {
+    /// Sets the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`].
+    pub fn config(self, config: PgConfig) -> PgLitBuilder<PgConfig, T> {
+        PgLitBuilder {
+            config,
+            tls: self.tls,
+        }
+    }
+}
+
+impl<C> PgLitBuilder<C, Unset> {
+    /// Sets the TLS connector, such as [`NoTls`][`deadpool_postgres::tokio_postgres::NoTls`].
+    pub fn tls<T>(self, tls: T) -> PgLitBuilder<C, T>
+    where
+        T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+        T::Stream: Sync + Send,
+        T::TlsConnect: Sync + Send,
+        <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+    {
+        PgLitBuilder {
+            config: self.config,
+            tls,
+        }
+    }
+}
+
+impl<T> PgLitBuilder<PgConfig, T>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    /// Builds the [`PgLit`].
+    pub fn build(self) -> PgLit<T> {
+        PgLit::new(self.config, self.tls)
+    }
+}
+
+impl<C, T> fmt::Debug for PgLitBuilder<C, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PgLitBuilder").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,30 @@
+use deadpool_postgres::tokio_postgres::Config as PgConfig;
+use testcontainers::{Container, Image};
+
+/// Port the PostgreSQL server listens on inside the container.
+const POSTGRES_PORT: u16 = 5432;
+
+/// Builds a [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`] connecting to the PostgreSQL server running in a `testcontainers` [`Container`].
+///
+/// The config points at the IPv4 host port mapped to the `5432` port of the container and uses the `user` and `password` the image was started with,
+/// so [`create_db`][`crate::create_db`] or [`connect`][`crate::connect`] can be called right away.
+///
+/// # Panics
+///
+/// This function will panic if the `5432` port of the container isn't mapped to a host port.
+pub fn config_from_container<I: Image>(
+    container: &Container<'_, I>,
+    user: &str,
+    password: &str,
+) -> PgConfig {
+    let port = container.get_host_port_ipv4(POSTGRES_PORT);
+    config_from_host_port("127.0.0.1", port, user, password)
+}
+
+/// Builds a [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`] connecting to the `host` and `port` with the `user` and `password`,
+/// such as the address of a PostgreSQL container started by other means.
+pub fn config_from_host_port(host: &str, port: u16, user: &str, password: &str) -> PgConfig {
+    let mut config = PgConfig::new();
+    let _ = config.host(host).port(port).user(user).password(password);
+    config
+}
@@ -0,0 +1,65 @@
+use tokio::sync::mpsc::Sender;
+
+use crate::CustomError;
+
+/// A lifecycle event of a create or drop database operation.
+///
+/// Events are sent by [`create_db_with_events`][`crate::create_db_with_events`] and [`drop_db_with_events`][`crate::drop_db_with_events`] in the order they happen.
+/// They never carry the password of the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PglitEvent {
+    /// The operation started.
+    Started {
+        /// `CREATE`, `DROP`, `DROP IF EXISTS` or `FORCEDROP`.
+        action: &'static str,
+        /// The name of the database the operation applies to.
+        db_name: String,
+    },
+    /// The connection to the admin database was established.
+    AdminConnected {
+        /// The admin database connected to, `postgres` unless the server lacks it and `template1` was used instead.
+        admin_db: String,
+    },
+    /// The statement was sent to the server.
+    StatementSent {
+        /// The SQL statement.
+        statement: String,
+    },
+    /// The operation succeeded.
+    Completed {
+        /// The number of rows reported by the server.
+        rows: u64,
+    },
+    /// The operation failed.
+    Failed {
+        /// The SQLSTATE code of the error.
+        code: String,
+        /// The error message.
+        message: String,
+    },
+}
+
+impl PglitEvent {
+    /// Returns the `Completed` or `Failed` event matching the `result`.
+    pub(crate) fn finished(result: &Result<u64, CustomError>) -> PglitEvent {
+        match result {
+            Ok(rows) => PglitEvent::Completed { rows: *rows },
+            Err(e) => PglitEvent::Failed {
+                code: e.code.clone(),
+                message: e.message.clone(),
+            },
+        }
+    }
+}
+
+/// Sends the event built by `event` if there is an observer.
+///
+/// The event is only built when `events` is `Some`, and a closed receiver is ignored.
+pub(crate) async fn emit<E>(events: Option<&Sender<PglitEvent>>, event: E)
+where
+    E: FnOnce() -> PglitEvent,
+{
+    if let Some(events) = events {
+        let _ = events.send(event()).await;
+    }
+}
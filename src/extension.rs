@@ -0,0 +1,107 @@
+use deadpool_postgres::tokio_postgres::Client;
+
+use crate::utils::{quote_identifier, quote_literal};
+use crate::CustomError;
+
+/// Installs an extension by running `CREATE EXTENSION [IF NOT EXISTS] name [SCHEMA schema_name]`.
+///
+/// If `if_not_exists` is set to true an extension that is already installed is reported as `Ok(())` instead of the *"42710"* error.
+/// The objects of the extension are created in the `schema` if any, which has to exist, otherwise in the current schema.
+///
+/// Note that by default the `extension_name` and `schema` arguments aren't enclosed in **double quotes** (").
+/// The **`quotes`** feature has to be enabled to use extensions with a hyphenated name such as `uuid-ossp`.
+///
+/// Obtain a [`Result<(), CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `extension_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// An extension that isn't available on the server is reported with the *"0A000"* code.
+pub async fn create_extension<F, U>(
+    client: &Client,
+    extension_name: &str,
+    if_not_exists: bool,
+    schema: Option<&str>,
+    mut cb: F,
+) -> U
+where
+    F: FnMut(Result<(), CustomError>) -> U,
+{
+    if extension_name.is_empty() {
+        panic!("the `extension_name` argument should not be empty");
+    }
+    let mut statement = String::from("CREATE EXTENSION ");
+    if if_not_exists {
+        statement.push_str("IF NOT EXISTS ");
+    }
+    statement.push_str(&quote_identifier(extension_name));
+    if let Some(schema) = schema {
+        statement.push_str(&format!(" SCHEMA {}", quote_identifier(schema)));
+    }
+    statement.push(';');
+    cb(client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new))
+}
+
+/// Checks if an extension is installed in the database the `client` is connected to, such as before installing it.
+///
+/// The `extension_name` is compared with the names stored in `pg_extension`, such as `uuid-ossp`.
+/// The check is reported as `false` if the query fails.
+///
+/// # Panics
+///
+/// This function will panic if the `extension_name` argument is empty.
+pub async fn extension_exists(client: &Client, extension_name: &str) -> bool {
+    if extension_name.is_empty() {
+        panic!("the `extension_name` argument should not be empty");
+    }
+    let statement = include_str!("../sql/fetch_extension.sql").trim();
+    client
+        .query(statement, &[&extension_name])
+        .await
+        .map(|rows| !rows.is_empty())
+        .unwrap_or(false)
+}
+
+/// Updates an installed extension by running `ALTER EXTENSION name UPDATE [TO 'version']`.
+///
+/// When `version` is `None` the extension is updated to the default version of its control file.
+/// The version is always sent as a string literal.
+///
+/// Note that by default the `extension_name` argument isn't enclosed in **double quotes** (").
+/// The **`quotes`** feature has to be enabled to use extensions with a hyphenated name such as `uuid-ossp`.
+///
+/// # Panics
+///
+/// This function will panic if the `extension_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn update_extension(
+    client: &Client,
+    extension_name: &str,
+    version: Option<&str>,
+) -> Result<(), CustomError> {
+    if extension_name.is_empty() {
+        panic!("the `extension_name` argument should not be empty");
+    }
+    let mut statement = format!(
+        "ALTER EXTENSION {} UPDATE",
+        quote_identifier(extension_name)
+    );
+    if let Some(version) = version {
+        statement.push_str(&format!(" TO {}", quote_literal(version)));
+    }
+    statement.push(';');
+    client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new)
+}
@@ -20,14 +20,80 @@
     unused_results
 )]
 
+use std::{future::Future, pin::Pin, sync::Arc};
+use tokio::sync::{mpsc::Sender, Semaphore};
+
 pub use deadpool_postgres;
 use deadpool_postgres::tokio_postgres::{
     tls::MakeTlsConnect, tls::TlsConnect, Client, Config as PgConfig, Connection,
     Error as TokioError, Socket,
 };
+mod admin;
+mod catalog;
+mod client;
+#[cfg(feature = "testcontainers")]
+mod container;
+mod events;
+mod extension;
+mod managed;
+mod migration;
+mod options;
+mod schema;
+mod sequence;
+mod session;
+mod spec;
+mod table;
+#[cfg(feature = "testing")]
+mod testing;
+mod transaction;
 mod utils;
+pub use admin::{
+    can_create_databases, checkpoint, connection_limit, create_db_with_client, database_exists,
+    drop_databases_with_prefix, drop_db_with_client, ensure_app_role, ensure_database,
+    forcedrop_db_with_client, grant_all_on_database, grant_privileges, list_databases,
+    provision_db, reconcile_db, reload_conf, rename_db, revoke_privileges, run_admin_query,
+    set_connection_limit, terminate_backend, with_admin_client, AdminSession, DatabaseInfo,
+};
+use admin::{create_missing_db, create_missing_pg_db};
+pub use catalog::{
+    cascade_dependents, database_object_summary, foreign_keys, list_extensions, list_indexes,
+    slow_queries, table_row_estimate, DbSummary, ExtensionInfo, ForeignKeyInfo, IndexInfo,
+    QueryStat,
+};
+pub use client::{PgLit, PgLitBuilder, Unset};
+#[cfg(feature = "testcontainers")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testcontainers")))]
+pub use container::{config_from_container, config_from_host_port};
+pub use events::PglitEvent;
+pub use extension::{create_extension, extension_exists, update_extension};
+pub use managed::{connect_managed, ManagedClient};
+pub use migration::apply_migrations;
+pub use options::{
+    AdminTarget, ColumnDef, Confirm, CreateDbOptions, DropDbOptions, GrantObject, GrantSpec,
+    IdentifierMode, Owner, RetryPolicy, SearchPathScope, VacuumOptions,
+};
+pub use schema::{
+    alter_schema_owner, drop_schema_if_empty, drop_schemas, list_schemas, schema_exists,
+};
+pub use sequence::{list_sequences, reset_sequence};
+pub use session::{reset_role, server_encoding, set_role, timezone, SessionInit};
+pub use spec::{apply_spec, DatabaseSpec, SchemaSpec, SpecOutcome};
+pub use table::{
+    analyze_table, column_exists, create_table, drop_table, qualified_table_exists, rename_table,
+    row_exists, table_privileges, tables_exist, truncate_all_tables, vacuum_table,
+};
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub use testing::assert_database_options;
+pub use transaction::begin_readonly_snapshot;
 pub use utils::errors::CustomError as CustomErrors;
-use utils::handle_db;
+pub use utils::errors::{
+    ActiveConnection, ConnectConfigError, ErrorKind, MigrationError, ReconcileError, VacuumError,
+};
+use utils::{
+    escape_identifier, handle_db, is_connection_error, quote_identifier, schema_or_public,
+};
+pub use utils::{is_valid_identifier, quote_identifier_checked, redacted_conninfo};
 
 #[doc = "Type alias for using [`CustomError`][CustomErrors] with [`tokio_postgres`][`deadpool_postgres::tokio_postgres`]."]
 pub type CustomError = CustomErrors;
@@ -41,7 +107,8 @@ pub type CustomError = CustomErrors;
 ///  
 /// The database name in [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`] will be ignored and replaced with the `db_name` argument.
 ///
-/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure, an existing database being reported as the *"42P04"* error.
+/// See [`create_db_if_missing`] to create the database only when it doesn't exist.
 ///
 /// # Panics
 ///  
@@ -79,23 +146,18 @@ where
     T::TlsConnect: Sync + Send,
     <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
 {
-    handle_db(config, db_name, tls, cb, "CREATE").await
+    create_db_with_options(config, db_name, tls, &CreateDbOptions::default(), cb).await
 }
 
-/// Dropes a database using the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`].
+/// Same as [`create_db`] but returns the result instead of passing it to a callback.
 ///
 /// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
-/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
-///
-/// To Learn more about PostgreSQL's Syntax Refer to [PosgreSql doc](https://www.postgresql.org/docs/current/sql-syntax-lexical.html#SQL-SYNTAX-IDENTIFIERS).
-///  
-/// The database name in [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`] will be ignored and replaced with the `db_name` argument.
-/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+/// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
 ///
 /// # Panics
-///  
+///
 /// This function will panic if the `db_name` argument is empty.
-///  
+///
 /// # Errors
 ///
 /// See [`CustomError`] for details.
@@ -104,54 +166,101 @@ where
 ///
 /// ```
 /// use tokio_postgres::{config::Config,NoTls};
-/// use pglit::drop_db;
-///async fn drop_the_db() {
+/// use pglit::{try_create_db, CustomError};
+///
+///async fn create_the_db() -> Result<(), CustomError> {
 ///    let mut config = Config::new();
 ///    config.user("testuser");
 ///    config.password("secretPassword");
-///    config.dbname("testdb");
 ///
-///    drop_db(&mut config, "testdb", NoTls, |result| match result {
-///        Ok(_n) => println!("database successfully dropped"),
-///        Err(e) => println!("pg_error ,{:?}", e),
-///    })
-///    .await
+///    let _n = try_create_db(&mut config, "testdb", NoTls).await?;
+///    Ok(())
 ///}
 /// ```
 ///
-pub async fn drop_db<T, F, U>(config: &mut PgConfig, db_name: &str, tls: T, cb: F) -> U
+pub async fn try_create_db<T>(
+    config: &mut PgConfig,
+    db_name: &str,
+    tls: T,
+) -> Result<u64, CustomError>
 where
-    F: FnMut(Result<u64, CustomError>) -> U,
     T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
     T::Stream: Sync + Send,
     T::TlsConnect: Sync + Send,
     <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
 {
-    handle_db(config, db_name, tls, cb, "DROP").await
+    create_db(config, db_name, tls, |result| result).await
 }
 
-/// Force drop a database using the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`].
+/// Creates a new database using the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`] unless it already exists.
 ///
-/// This function will force drop the database using the **_Force_** option introduced in `PostgreSQL 13`.
+/// This is the recommended way to create a database: the *"42P04", "Attempting to create a duplicate database."* postgres error is handled here,
+/// so callers don't have to match on it. Use [`create_db`] to get that error instead.
 ///
-/// # Details
-/// From the [postgres doc](https://www.postgresql.org/docs/current/sql-dropdatabase.html) the **_Force_** option:
+/// Returns `true` if the database was created and `false` if it already existed.
 ///
-/// Attempt to terminate all existing connections to the target database.
-/// It doesn't terminate if prepared transactions, active logical replication slots or subscriptions are present in the target database.\
-/// This will fail if the current user has no permissions to terminate other connections.<br/>
-/// To learn more refer to [postgres doc](https://www.postgresql.org/docs/current/sql-dropdatabase.html)
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes** (").
+/// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
 ///
-/// # Important
-/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
-/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
 ///
+/// # Errors
 ///
-/// Obtain a [Result<u64, CustomError>] via a callback Closure
+/// See [`CustomError`] for details.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::{config::Config,NoTls};
+/// use pglit::create_db_if_missing;
+///
+///async fn create_the_db() {
+///    let mut config = Config::new();
+///    config.user("testuser");
+///    config.password("secretPassword");
+///
+///    match create_db_if_missing(&mut config, "testdb", NoTls).await {
+///        Ok(true) => println!("database successfully created"),
+///        Ok(false) => println!("database already exists"),
+///        Err(e) => println!("pg_error ,{:?}", e),
+///    }
+///}
+/// ```
+///
+pub async fn create_db_if_missing<T>(
+    config: &mut PgConfig,
+    db_name: &str,
+    tls: T,
+) -> Result<bool, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    create_db(config, db_name, tls, |result| match result {
+        Ok(_n) => Ok(true),
+        Err(e) if e.code == "42P04" => Ok(false),
+        Err(e) => Err(e),
+    })
+    .await
+}
+
+/// Same as [`create_db`] but an existing database is reported as `Ok(0)` instead of the *"42P04", "Attempting to create a duplicate database."* error.
+///
+/// The callback is the same as the one of [`create_db`], so this function is a drop-in replacement for an idempotent setup.
+/// Use [`create_db_if_missing`] to know whether the database was created.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes** (").
+/// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
 ///
 /// # Panics
-///  
-/// This function will panic if the `db_name` argument is empty.  
+///
+/// This function will panic if the `db_name` argument is empty.
 ///
 /// # Errors
 ///
@@ -161,22 +270,27 @@ where
 ///
 /// ```
 /// use tokio_postgres::{config::Config,NoTls};
-/// use pglit::drop_db;
-///async fn force_drop_the_db() {
+/// use pglit::create_db_if_not_exists;
+///
+///async fn setup_db() {
 ///    let mut config = Config::new();
 ///    config.user("testuser");
 ///    config.password("secretPassword");
-///    config.dbname("testdb");
 ///
-///    forcedrop_db(&mut config, "testdb", NoTls, |result| match result {
-///        Ok(_n) => println!("database successfully dropped"),
+///    create_db_if_not_exists(&mut config, "testdb", NoTls, |result| match result {
+///        Ok(_n) => println!("database ready"),
 ///        Err(e) => println!("pg_error ,{:?}", e),
 ///    })
 ///    .await
 ///}
 /// ```
 ///
-pub async fn forcedrop_db<T, F, U>(config: &mut PgConfig, db_name: &str, tls: T, cb: F) -> U
+pub async fn create_db_if_not_exists<T, F, U>(
+    config: &mut PgConfig,
+    db_name: &str,
+    tls: T,
+    mut cb: F,
+) -> U
 where
     F: FnMut(Result<u64, CustomError>) -> U,
     T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
@@ -184,204 +298,1194 @@ where
     T::TlsConnect: Sync + Send,
     <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
 {
-    handle_db(config, db_name, tls, cb, "DROP, WITH (FORCE);").await
+    let cb = |result: Result<u64, CustomError>| match result {
+        Err(e) if e.code == "42P04" => cb(Ok(0)),
+        result => cb(result),
+    };
+    handle_db(
+        config,
+        db_name,
+        tls,
+        cb,
+        "CREATE",
+        None,
+        IdentifierMode::default(),
+        None,
+        None,
+        None,
+    )
+    .await
 }
 
-use {
-    deadpool::managed::BuildError,
-    deadpool_postgres::CreatePoolError,
-    deadpool_postgres::{Config as dpConfig, Pool, Runtime},
-};
-
-/// Convenient function to create a database and get a connection pool using the [`deadpool_postgres`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres) crate .
+/// Creates a new database using the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`] and the [`CreateDbOptions`].
 ///
-///This function will attempt to create a database by using the [`deadpool_postgres`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres) crate to return a [`Pool`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.Pool.html),
-///and it will handle the *"42P04", "Attempting to create a duplicate database."* postgres error if returned.
+/// This function behaves like [`create_db`] but appends the `options` to the `CREATE DATABASE` statement, such as the `OWNER` of the new database.
 ///
-/// # Important
-/// Note that by default the `dbname` in the `config` shouldn't be enclosed in **double quotes**.
-/// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+/// Note that by default the `db_name` argument and the [`Owner::Named`] role shouldn't be enclosed in **double quotes** (").
+/// To use names enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled,
+/// or the `identifier_mode` of the `options` set to [`IdentifierMode::Quoted`].
 ///
-/// # Errors
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
 ///
-/// See [`CreatePoolError`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.CreatePoolError.html) for details.
-///  
-/// # Example
+/// # Panics
 ///
-///```rust
-/// #[derive(serde::Deserialize, Debug)]
-/// pub struct Config {
-///     pub pg: deadpool_postgres::Config,
-/// }
-/// impl Config {
-///     pub fn from_env() -> Result<Self, config::ConfigError> {
-///         ::config::Config::builder()
-///             .add_source(::config::Environment::default())
-///             .build()?
-///             .try_deserialize()
-///     }
-/// }
+/// This function will panic if the `db_name` argument is empty.
 ///
-/// async fn create_db_and_get_pool() {
-/// dotenv().ok();
-/// let cfg = Config::from_env().unwrap();
-/// let cfg = cfg.pg;
+/// # Errors
 ///
-/// let result = deadpool_create_db(cfg, None, NoTls).await;
-/// if let Ok(pool) = &result {
-///     let p = pool.get().await;
-///     match &p {
-///         Ok(_obj) => {
-///             println!("pool object created & returned");
-///         }
-///         Err(e) => {
-///             println!("error from pool {:?}", e);
-///         }
-///     };
-///   }
-/// }
-///```
+/// See [`CustomError`] for details.
 ///
+/// # Example
 ///
+/// ```
+/// use tokio_postgres::{config::Config,NoTls};
+/// use pglit::{create_db_with_options, CreateDbOptions, Owner};
 ///
-
-pub async fn deadpool_create_db<T>(
-    config: dpConfig,
-    runtime: Option<Runtime>,
+///async fn create_tenant_db() {
+///    let mut config = Config::new();
+///    config.user("testuser");
+///    config.password("secretPassword");
+///
+///    let options = CreateDbOptions {
+///        owner: Some(Owner::Named("tenant_role".to_string())),
+///        ..CreateDbOptions::default()
+///    };
+///    create_db_with_options(&mut config, "tenantdb", NoTls, &options, |result| match result {
+///        Ok(_n) => println!("database successfully created"),
+///        Err(e) => println!("pg_error ,{:?}", e),
+///    })
+///    .await
+///}
+/// ```
+///
+pub async fn create_db_with_options<T, F, U>(
+    config: &mut PgConfig,
+    db_name: &str,
     tls: T,
-) -> Result<Pool, CreatePoolError>
+    options: &CreateDbOptions,
+    cb: F,
+) -> U
 where
+    F: FnMut(Result<u64, CustomError>) -> U,
     T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
     T::Stream: Sync + Send,
     T::TlsConnect: Sync + Send,
     <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
 {
-    let mut pgconfig = config
-        .get_pg_config()
-        .map_err(deadpool::managed::CreatePoolError::Config)?;
-
-    let db_name = config.dbname.clone().unwrap();
-
-    create_db(&mut pgconfig, &db_name, tls.clone(), |res| match res {
-        Ok(_r) => config.create_pool(runtime, tls.clone()),
-        Err(e) => {
-            if e.code == "42P04" {
-                config.create_pool(runtime, tls.clone())
-            } else {
-                let err =
-                    deadpool::managed::CreatePoolError::Build(BuildError::Backend(e.pg_error));
-                Err(err)
-            }
-        }
-    })
+    handle_db(
+        config,
+        db_name,
+        tls,
+        cb,
+        "CREATE",
+        Some(options),
+        options.identifier_mode,
+        options.admin_target.as_ref(),
+        options.timeout,
+        None,
+    )
     .await
 }
-///Convenient function that attempts to establish a connection with `db_name` and then return [`tokio_postgres`][`deadpool_postgres::tokio_postgres`] [`Client`].
+
+/// Creates a new database owned by the `owner` role, rendered as `CREATE DATABASE db_name OWNER owner`.
 ///
-/// This function will attempt to establish a connection using the `db_name` argument and it will handle the *"42P04", "Attempting to create a duplicate database."* postgres error if returned, by creating a new database named after the `db_name` argument
-/// and then returns a [`tokio_postgres`][`deadpool_postgres::tokio_postgres`] [`Client`].
+/// This is a shorthand for [`create_db_with_options`] with an [`Owner::Named`] owner, such as for per-tenant databases owned by the role of the tenant.
 ///
-/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
-/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+/// Note that by default the `db_name` and `owner` arguments shouldn't be enclosed in **double quotes** (").
+/// To use names enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
 ///
-/// To Learn more about PostgreSQL's Syntax Refer to [PosgreSql doc](https://www.postgresql.org/docs/current/sql-syntax-lexical.html#SQL-SYNTAX-IDENTIFIERS).
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
 ///
-/// # Errors
+/// # Panics
 ///
-/// See [`tokio_postgres::error`][`deadpool_postgres::tokio_postgres::error`] for details.
+/// This function will panic if the `db_name` or the `owner` argument is empty.
 ///
+/// # Errors
 ///
-pub async fn connect<T>(
-    mut config: PgConfig,
+/// See [`CustomError`] for details.\
+/// A role that doesn't exist is reported with the *"42704"* code.
+pub async fn create_db_with_owner<T, F, U>(
+    config: &mut PgConfig,
     db_name: &str,
+    owner: &str,
     tls: T,
-) -> Result<(Client, Connection<Socket, T::Stream>), TokioError>
+    cb: F,
+) -> U
 where
+    F: FnMut(Result<u64, CustomError>) -> U,
     T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
     T::Stream: Sync + Send,
     T::TlsConnect: Sync + Send,
     <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
 {
-    let _ = config.dbname(db_name);
-    let client_result = create_db(&mut config.clone(), db_name, tls.clone(), |result| async {
-        match result {
-            Ok(_n) => config.connect(tls.clone()).await,
-            Err(e) => {
-                if e.code == "42P04" {
-                    config.connect(tls.clone()).await
-                } else {
-                    Err(e.pg_error)
-                }
-            }
-        }
-    })
-    .await;
-    client_result.await
+    if owner.is_empty() {
+        panic!("the `owner` argument should not be empty");
+    }
+    let options = CreateDbOptions {
+        owner: Some(Owner::Named(owner.to_string())),
+        ..CreateDbOptions::default()
+    };
+    create_db_with_options(config, db_name, tls, &options, cb).await
 }
 
-/// Checks if a table exist in a particular schema in the database.
+/// Creates the `new_db` database as a copy of the `source_db` database, rendered as `CREATE DATABASE new_db TEMPLATE source_db`.
 ///
-/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+/// This is a shorthand for [`create_db_with_options`] with a `template`, such as for creating a throwaway database per test from a seeded database.
+///
+/// Note that by default the `source_db` and `new_db` arguments shouldn't be enclosed in **double quotes** (").
+/// To use names enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `source_db` or the `new_db` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// The `source_db` database can't be copied while other sessions are connected to it, in which case
+/// *"55006", "source database is being accessed by other users"* is returned.
+pub async fn clone_db<T, F, U>(
+    config: &mut PgConfig,
+    source_db: &str,
+    new_db: &str,
+    tls: T,
+    cb: F,
+) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    if source_db.is_empty() {
+        panic!("The database name in the `source_db` argument should not be empty");
+    }
+    if new_db.is_empty() {
+        panic!("The database name in the `new_db` argument should not be empty");
+    }
+    let options = CreateDbOptions {
+        template: Some(source_db.to_string()),
+        ..CreateDbOptions::default()
+    };
+    create_db_with_options(config, new_db, tls, &options, cb).await
+}
+
+/// Dropes a database using the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`].
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
+/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
 ///
+/// To Learn more about PostgreSQL's Syntax Refer to [PosgreSql doc](https://www.postgresql.org/docs/current/sql-syntax-lexical.html#SQL-SYNTAX-IDENTIFIERS).
 ///  
-/// Returns a [`bool`] via a callback Closure
+/// The database name in [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`] will be ignored and replaced with the `db_name` argument.
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
 ///
 /// # Panics
 ///  
-/// This function will panic if the `table_name` argument is empty.   
+/// This function will panic if the `db_name` argument is empty.
+///  
+/// # Errors
 ///
+/// See [`CustomError`] for details.
 ///
 /// # Example
 ///
 /// ```
 /// use tokio_postgres::{config::Config,NoTls};
-/// use pglit::table_exists;
-/// async fn tables_exist() {
-///     let mut config = Config::new();
-///     config.user("testuser");
-///     config.password("secretPassword");
-///     config.dbname("testdb");
-///     let (client, connection) = config.connect(NoTls).await.unwrap();
-///     tokio::spawn(async move {
-///         if let Err(e) = connection.await {
-///             eprintln!("connection error: {}", e);
-///         }
-///     });
-///     let tb_exist = table_exists(&client, "", "pglit_table").await;
-///     assert!(!tb_exist);
-/// }
-/// ```
-///
-pub async fn table_exists(client: &Client, schema_name: &str, table_name: &str) -> bool {
-    if table_name.is_empty() {
-        panic!("the `table_name` argument should not be empty");
+/// use pglit::drop_db;
+///async fn drop_the_db() {
+///    let mut config = Config::new();
+///    config.user("testuser");
+///    config.password("secretPassword");
+///    config.dbname("testdb");
+///
+///    drop_db(&mut config, "testdb", NoTls, |result| match result {
+///        Ok(_n) => println!("database successfully dropped"),
+///        Err(e) => println!("pg_error ,{:?}", e),
+///    })
+///    .await
+///}
+/// ```
+///
+pub async fn drop_db<T, F, U>(config: &mut PgConfig, db_name: &str, tls: T, cb: F) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    handle_db(
+        config,
+        db_name,
+        tls,
+        cb,
+        "DROP",
+        None,
+        IdentifierMode::default(),
+        None,
+        None,
+        None,
+    )
+    .await
+}
+
+/// Same as [`drop_db`] but returns the result instead of passing it to a callback.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
+/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn try_drop_db<T>(
+    config: &mut PgConfig,
+    db_name: &str,
+    tls: T,
+) -> Result<u64, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    drop_db(config, db_name, tls, |result| result).await
+}
+
+/// Same as [`drop_db`] but runs `DROP DATABASE IF EXISTS`, so a database that doesn't exist is reported as `Ok(0)`
+/// instead of the *"3D000", "database does not exist"* error.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
+/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn drop_db_if_exists<T, F, U>(config: &mut PgConfig, db_name: &str, tls: T, cb: F) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    handle_db(
+        config,
+        db_name,
+        tls,
+        cb,
+        "DROP IF EXISTS",
+        None,
+        IdentifierMode::default(),
+        None,
+        None,
+        None,
+    )
+    .await
+}
+
+/// Force drop a database using the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`].
+///
+/// This function will force drop the database using the **_Force_** option introduced in `PostgreSQL 13`.
+///
+/// # Details
+/// From the [postgres doc](https://www.postgresql.org/docs/current/sql-dropdatabase.html) the **_Force_** option:
+///
+/// Attempt to terminate all existing connections to the target database.
+/// It doesn't terminate if prepared transactions, active logical replication slots or subscriptions are present in the target database.\
+/// This will fail if the current user has no permissions to terminate other connections.<br/>
+/// To learn more refer to [postgres doc](https://www.postgresql.org/docs/current/sql-dropdatabase.html)
+///
+/// # Important
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
+/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+///
+/// Obtain a [Result<u64, CustomError>] via a callback Closure
+///
+/// # Panics
+///  
+/// This function will panic if the `db_name` argument is empty.  
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+///
+/// The `confirm` token obtained through [`Confirm::force`] makes the destructive nature of the call explicit,
+/// see [`forcedrop_db_unguarded`] for a variant without it.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::{config::Config,NoTls};
+/// use pglit::{forcedrop_db, Confirm};
+///async fn force_drop_the_db() {
+///    let mut config = Config::new();
+///    config.user("testuser");
+///    config.password("secretPassword");
+///    config.dbname("testdb");
+///
+///    forcedrop_db(&mut config, "testdb", NoTls, Confirm::force(), |result| match result {
+///        Ok(_n) => println!("database successfully dropped"),
+///        Err(e) => println!("pg_error ,{:?}", e),
+///    })
+///    .await
+///}
+/// ```
+///
+pub async fn forcedrop_db<T, F, U>(
+    config: &mut PgConfig,
+    db_name: &str,
+    tls: T,
+    _confirm: Confirm,
+    cb: F,
+) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    forcedrop_db_unguarded(config, db_name, tls, cb).await
+}
+
+/// Same as [`forcedrop_db`] but returns the result instead of passing it to a callback.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
+/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn try_forcedrop_db<T>(
+    config: &mut PgConfig,
+    db_name: &str,
+    tls: T,
+    confirm: Confirm,
+) -> Result<u64, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    forcedrop_db(config, db_name, tls, confirm, |result| result).await
+}
+
+/// Same as [`forcedrop_db`] without the [`Confirm`] token, for automation such as test teardown.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn forcedrop_db_unguarded<T, F, U>(
+    config: &mut PgConfig,
+    db_name: &str,
+    tls: T,
+    cb: F,
+) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    handle_db(
+        config,
+        db_name,
+        tls,
+        cb,
+        "DROP, WITH (FORCE);",
+        None,
+        IdentifierMode::default(),
+        None,
+        None,
+        None,
+    )
+    .await
+}
+
+/// Drops a database using the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`] and the [`DropDbOptions`].
+///
+/// This function behaves like [`drop_db`], or like [`forcedrop_db`] when the `force` option is set,
+/// and runs the statement through the [`AdminTarget`] of the `options` if any, such as the primary of a replicated cluster.
+///
+/// The `db_name` argument is written according to the `identifier_mode` of the `options`, see [`IdentifierMode`].
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn drop_db_with_options<T, F, U>(
+    config: &mut PgConfig,
+    db_name: &str,
+    tls: T,
+    options: &DropDbOptions,
+    cb: F,
+) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let action = if options.force {
+        "DROP, WITH (FORCE);"
+    } else {
+        "DROP"
+    };
+    handle_db(
+        config,
+        db_name,
+        tls,
+        cb,
+        action,
+        None,
+        options.identifier_mode,
+        options.admin_target.as_ref(),
+        options.timeout,
+        None,
+    )
+    .await
+}
+
+/// Creates a new database like [`create_db_with_options`], sending its lifecycle events to the `events` channel.
+///
+/// The events are sent in order: [`PglitEvent::Started`], [`PglitEvent::AdminConnected`], [`PglitEvent::StatementSent`],
+/// then [`PglitEvent::Completed`] or [`PglitEvent::Failed`] before the callback is called.
+/// The events never carry the password of the `config`, and they are dropped if the receiver is closed.
+///
+/// Note that the channel is bounded, so the operation waits while it is full.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn create_db_with_events<T, F, U>(
+    config: &mut PgConfig,
+    db_name: &str,
+    tls: T,
+    options: &CreateDbOptions,
+    events: &Sender<PglitEvent>,
+    cb: F,
+) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    handle_db(
+        config,
+        db_name,
+        tls,
+        cb,
+        "CREATE",
+        Some(options),
+        options.identifier_mode,
+        options.admin_target.as_ref(),
+        options.timeout,
+        Some(events),
+    )
+    .await
+}
+
+/// Drops a database like [`drop_db`], or like [`forcedrop_db`] if `force` is `true`, sending its lifecycle events to the `events` channel.
+///
+/// See [`create_db_with_events`] for details about the events.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn drop_db_with_events<T, F, U>(
+    config: &mut PgConfig,
+    db_name: &str,
+    tls: T,
+    force: bool,
+    events: &Sender<PglitEvent>,
+    cb: F,
+) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let action = if force { "DROP, WITH (FORCE);" } else { "DROP" };
+    handle_db(
+        config,
+        db_name,
+        tls,
+        cb,
+        action,
+        None,
+        IdentifierMode::default(),
+        None,
+        None,
+        Some(events),
+    )
+    .await
+}
+
+use {
+    deadpool::managed::BuildError,
+    deadpool_postgres::CreatePoolError,
+    deadpool_postgres::{Config as dpConfig, Manager, Object, Pool, PoolError, Runtime},
+};
+
+/// Convenient function to create a database and get a connection pool using the [`deadpool_postgres`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres) crate .
+///
+///This function will attempt to create a database by using the [`deadpool_postgres`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres) crate to return a [`Pool`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.Pool.html),
+///and it will handle the *"42P04", "Attempting to create a duplicate database."* postgres error if returned.
+///
+/// The database is created through a short-lived [`AdminSession`] which is closed before the pool is built.
+/// The pool connects to the `dbname` database and not to the `postgres` admin database, so it can't reuse the admin connection
+/// and the create and the pool each pay for their own connection handshake.
+///
+/// # Important
+/// Note that by default the `dbname` in the `config` shouldn't be enclosed in **double quotes**.
+/// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Errors
+///
+/// See [`CreatePoolError`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.CreatePoolError.html) for details.
+///  
+/// # Example
+///
+///```rust
+/// #[derive(serde::Deserialize, Debug)]
+/// pub struct Config {
+///     pub pg: deadpool_postgres::Config,
+/// }
+/// impl Config {
+///     pub fn from_env() -> Result<Self, config::ConfigError> {
+///         ::config::Config::builder()
+///             .add_source(::config::Environment::default())
+///             .build()?
+///             .try_deserialize()
+///     }
+/// }
+///
+/// async fn create_db_and_get_pool() {
+/// dotenv().ok();
+/// let cfg = Config::from_env().unwrap();
+/// let cfg = cfg.pg;
+///
+/// let result = deadpool_create_db(cfg, None, NoTls).await;
+/// if let Ok(pool) = &result {
+///     let p = pool.get().await;
+///     match &p {
+///         Ok(_obj) => {
+///             println!("pool object created & returned");
+///         }
+///         Err(e) => {
+///             println!("error from pool {:?}", e);
+///         }
+///     };
+///   }
+/// }
+///```
+///
+pub async fn deadpool_create_db<T>(
+    config: dpConfig,
+    runtime: Option<Runtime>,
+    tls: T,
+) -> Result<Pool, CreatePoolError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let pgconfig = config
+        .get_pg_config()
+        .map_err(deadpool::managed::CreatePoolError::Config)?;
+
+    let db_name = config.dbname.clone().unwrap();
+
+    match create_missing_db(&pgconfig, &db_name, tls.clone()).await {
+        Ok(_created) => config.create_pool(runtime, tls),
+        Err(e) => Err(e.into_create_pool_error()),
+    }
+}
+
+/// Same as [`deadpool_create_db`] but every connection of the returned pool starts with the session state of `session`,
+/// such as a default `search_path` pointing at a tenant schema.
+///
+/// The settings are added to the startup options of the pooled connections, so the server applies them when each connection is opened and
+/// a checked out connection is already configured without a `SET` round trip. A `RESET ALL` on a connection also restores them.
+/// The database itself is created with the plain `config`. See [`SessionInit`] for details.
+///
+/// # Important
+/// Note that by default the `dbname` in the `config` shouldn't be enclosed in **double quotes**.
+/// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Errors
+///
+/// See [`CreatePoolError`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.CreatePoolError.html) for details.\
+/// An invalid setting, such as a role the user isn't a member of, is reported when a connection is checked out of the pool.
+pub async fn deadpool_create_db_with_session<T>(
+    mut config: dpConfig,
+    runtime: Option<Runtime>,
+    tls: T,
+    session: &SessionInit,
+) -> Result<Pool, CreatePoolError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let pgconfig = config
+        .get_pg_config()
+        .map_err(deadpool::managed::CreatePoolError::Config)?;
+
+    let db_name = config.dbname.clone().unwrap();
+
+    if let Err(e) = create_missing_db(&pgconfig, &db_name, tls.clone()).await {
+        return Err(e.into_create_pool_error());
+    }
+    let mut session_config = PgConfig::new();
+    if let Some(options) = &config.options {
+        let _ = session_config.options(options.as_str());
+    }
+    session.configure(&mut session_config);
+    config.options = session_config.get_options().map(str::to_string);
+    config.create_pool(runtime, tls)
+}
+
+/// Same as [`deadpool_create_db`] but retries the connection-level failures according to the `retry` policy.
+///
+/// Right after a restart or a failover, the server may refuse connections for a short while. This function retries:
+/// - the admin connection used to create the database.
+/// - a first connection checked out of the returned pool, because the pool itself connects lazily and would only fail on the first [`Pool::get`].
+///
+/// Only connection-level failures are retried, such as I/O errors, closed connections or a server that is still starting up (*"57P03"*).
+/// Config errors and Postgres errors such as an authentication failure are returned immediately.
+///
+/// The other errors of the first checkout, such as a create timeout of the pool, are returned as a [`BuildError::NoRuntimeSpecified`] carrying their message.
+///
+/// # Important
+/// Note that by default the `dbname` in the `config` shouldn't be enclosed in **double quotes**.
+/// To create a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Errors
+///
+/// See [`CreatePoolError`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.CreatePoolError.html) for details.
+///
+pub async fn deadpool_create_db_with_retry<T>(
+    config: dpConfig,
+    runtime: Option<Runtime>,
+    tls: T,
+    retry: RetryPolicy,
+) -> Result<Pool, CreatePoolError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let pgconfig = config
+        .get_pg_config()
+        .map_err(deadpool::managed::CreatePoolError::Config)?;
+
+    let db_name = config.dbname.clone().unwrap();
+
+    let mut attempt = 0;
+    loop {
+        match create_missing_db(&pgconfig, &db_name, tls.clone()).await {
+            Err(e) if attempt < retry.max_retries && e.kind() == ErrorKind::Connection => {
+                tokio::time::sleep(retry.backoff(attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into_create_pool_error()),
+            Ok(_created) => break,
+        }
+    }
+
+    let pool = config.create_pool(runtime, tls)?;
+    let mut attempt = 0;
+    loop {
+        match pool.get().await {
+            Err(PoolError::Backend(e))
+                if attempt < retry.max_retries && is_connection_error(&e) =>
+            {
+                tokio::time::sleep(retry.backoff(attempt)).await;
+                attempt += 1;
+            }
+            Err(PoolError::Backend(e)) => {
+                return Err(CreatePoolError::Build(BuildError::Backend(e)))
+            }
+            Err(e) => return Err(CustomError::pool(e).into_create_pool_error()),
+            Ok(_object) => return Ok(pool),
+        }
+    }
+}
+/// Checks that a [`Pool`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.Pool.html) can reach its database by checking out a connection and running `SELECT 1`.
+///
+/// A pool returned by [`deadpool_create_db`] doesn't connect until its first [`get`](https://docs.rs/deadpool/0.9.5/deadpool/managed/struct.Pool.html#method.get),
+/// calling this function on startup detects an unreachable database before the first real query.
+///
+/// # Errors
+///
+/// Returns a [`CustomError`] if the connection or the query fails.\
+/// If no connection could be checked out for another reason such as a timeout or a closed pool, the error has the `"pool"` code and no `pg_error`.
+pub async fn verify_pool(pool: &Pool) -> Result<(), CustomError> {
+    let client = pool.get().await.map_err(|e| match e {
+        PoolError::Backend(e) => CustomError::new(e),
+        e => CustomError::pool(e),
+    })?;
+    let _ = client
+        .query_one("SELECT 1", &[])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(())
+}
+
+/// Closes a [`Pool`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.Pool.html) and then drops the database it is connected to.
+///
+/// Dropping a database fails with *"55006", "database is being accessed by other users"* while the pool still holds idle connections to it,
+/// which is the case for a pool returned by [`deadpool_create_db`]. This function closes the `pool` first, so its idle connections are released before the drop is issued.\
+/// Connections currently checked out of the pool are released once they are returned, note that PostgreSQL only waits a few seconds for them before failing the drop.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
+/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<u64, CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `db_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+///
+pub async fn drop_db_with_pool<T, F, U>(
+    pool: &Pool,
+    config: &mut PgConfig,
+    db_name: &str,
+    tls: T,
+    cb: F,
+) -> U
+where
+    F: FnMut(Result<u64, CustomError>) -> U,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    pool.close();
+    handle_db(
+        config,
+        db_name,
+        tls,
+        cb,
+        "DROP",
+        None,
+        IdentifierMode::default(),
+        None,
+        None,
+        None,
+    )
+    .await
+}
+
+///Convenient function that attempts to establish a connection with `db_name` and then return [`tokio_postgres`][`deadpool_postgres::tokio_postgres`] [`Client`].
+///
+/// This function will attempt to establish a connection using the `db_name` argument and it will handle the *"42P04", "Attempting to create a duplicate database."* postgres error if returned, by creating a new database named after the `db_name` argument
+/// and then returns a [`tokio_postgres`][`deadpool_postgres::tokio_postgres`] [`Client`].
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
+/// To drop a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// To Learn more about PostgreSQL's Syntax Refer to [PosgreSql doc](https://www.postgresql.org/docs/current/sql-syntax-lexical.html#SQL-SYNTAX-IDENTIFIERS).
+///
+/// # Errors
+///
+/// See [`tokio_postgres::error`][`deadpool_postgres::tokio_postgres::error`] for details.
+///
+///
+pub async fn connect<T>(
+    config: PgConfig,
+    db_name: &str,
+    tls: T,
+) -> Result<(Client, Connection<Socket, T::Stream>), TokioError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    connect_with_retry(config, db_name, tls, RetryPolicy::never()).await
+}
+
+/// Same as [`connect`] but the [`tokio_postgres::Config`][`deadpool_postgres::tokio_postgres::Config`] and the database name are derived
+/// from a [`deadpool_postgres::Config`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/struct.Config.html), such as the one used to build the pool of the application.
+///
+/// The database named in the `config` is created if it is missing.
+///
+/// Note that by default the `dbname` in the `config` shouldn't be enclosed in **double quotes**.
+/// To use a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Errors
+///
+/// Returns [`ConnectConfigError::Config`] if the `config` is invalid, such as a missing `dbname`, and
+/// [`ConnectConfigError::Postgres`] if connecting fails, see [`connect`] for details.
+pub async fn connect_from_deadpool_config<T>(
+    config: &dpConfig,
+    tls: T,
+) -> Result<(Client, Connection<Socket, T::Stream>), ConnectConfigError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let pgconfig = config.get_pg_config().map_err(ConnectConfigError::Config)?;
+    // `get_pg_config` fails when the dbname is missing
+    let db_name = config.dbname.clone().unwrap_or_default();
+    connect(pgconfig, &db_name, tls)
+        .await
+        .map_err(ConnectConfigError::Postgres)
+}
+
+/// Same as [`connect`] but retries the final connection to `db_name` according to the `retry` policy.
+///
+/// Only connection-level failures are retried, such as I/O errors, closed connections or a server that is still starting up (*"57P03"*).
+/// This makes the connection robust during a failover or a restart of the server.\
+/// Postgres errors such as *"3D000"*, *"Attempting to connect to a database that doesn't exist."* or an authentication failure are returned immediately.
+///
+/// # Errors
+///
+/// See [`tokio_postgres::error`][`deadpool_postgres::tokio_postgres::error`] for details.
+///
+pub async fn connect_with_retry<T>(
+    mut config: PgConfig,
+    db_name: &str,
+    tls: T,
+    retry: RetryPolicy,
+) -> Result<(Client, Connection<Socket, T::Stream>), TokioError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let _ = config.dbname(db_name);
+    let _created = create_missing_pg_db(&config, db_name, tls.clone()).await?;
+
+    let mut attempt = 0;
+    loop {
+        match config.connect(tls.clone()).await {
+            Err(e) if attempt < retry.max_retries && is_connection_error(&e) => {
+                tokio::time::sleep(retry.backoff(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
     }
+}
 
-    let mut statement = include_str!("../sql/fetch_table_name.sql")
-        .trim()
-        .to_string();
-    statement = statement.replace("$table_name", table_name);
+/// Connects to `db_name` (creating it if needed, like [`connect`]), runs the `f` closure against the [`Client`] and then closes the connection.
+///
+/// The connection future is driven by a spawned task for as long as `f` runs. Once `f` completes, the [`Client`] is dropped
+/// and the task is awaited until the connection is shut down, so no connection task outlives this function.
+///
+/// The `f` closure returns a boxed future borrowing the [`Client`], see the example below.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details. Only the errors raised while connecting are returned here, the output of `f` is returned as is.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::{config::Config,NoTls};
+/// use pglit::with_client;
+///
+/// async fn count_students() {
+///     let mut config = Config::new();
+///     config.user("testuser");
+///     config.password("secretPassword");
+///     let count = with_client(config, "testdb", NoTls, |client| {
+///         Box::pin(async move {
+///             let row = client.query_one("SELECT count(*) FROM student", &[]).await?;
+///             Ok::<i64, tokio_postgres::Error>(row.get(0))
+///         })
+///     })
+///     .await;
+/// }
+/// ```
+///
+pub async fn with_client<T, F, R>(
+    config: PgConfig,
+    db_name: &str,
+    tls: T,
+    f: F,
+) -> Result<R, CustomError>
+where
+    F: for<'c> FnOnce(&'c Client) -> Pin<Box<dyn Future<Output = R> + Send + 'c>>,
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let (client, connection) = connect(config, db_name, tls)
+        .await
+        .map_err(CustomError::new)?;
+    let connection = tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let output = f(&client).await;
+    // dropping the client terminates the connection, which ends the task
+    drop(client);
+    let _ = connection.await;
+    Ok(output)
+}
 
-    if schema_name.is_empty() {
-        statement = statement.replace("$schema_name", "public");
-    } else {
-        statement = statement.replace("$schema_name", schema_name);
+/// Same as [`connect`] but the connection is opened with the session state of `session`.
+///
+/// The settings are sent as startup options of the connection to `db_name`, so the server applies them before the connection is returned, and every
+/// reconnection made with this function re-establishes the same session state. See [`SessionInit`] for details.
+///
+/// # Errors
+///
+/// See [`tokio_postgres::error`][`deadpool_postgres::tokio_postgres::error`] for details.\
+/// An invalid setting, such as a role the user isn't a member of, is reported when connecting.
+///
+pub async fn connect_with_session<T>(
+    mut config: PgConfig,
+    db_name: &str,
+    tls: T,
+    session: &SessionInit,
+) -> Result<(Client, Connection<Socket, T::Stream>), TokioError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    // the admin connection creating the database runs without the session settings
+    let _created = create_missing_pg_db(&config, db_name, tls.clone()).await?;
+    session.configure(&mut config);
+    let _ = config.dbname(db_name);
+    config.connect(tls).await
+}
+
+/// Same as [`connect`] but returns an [`Object`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.Object.html) of a single-connection [`Pool`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/type.Pool.html).
+///
+/// The connection future is driven by the pool, so there is no connection task to spawn, and the connection is closed when the object is dropped.
+/// This is meant for applications built on [`deadpool_postgres`] that only need one connection.
+///
+/// Like [`connect`], the `db_name` database is created if it doesn't exist, the returned boolean is `true` if the database was created or `false` if it already existed.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
+/// To use a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Errors
+///
+/// Returns a [`CustomError`] if creating the database or connecting to it fails.\
+/// If the connection couldn't be checked out of the pool for another reason, the error has the `"pool"` code and no `pg_error`.
+///
+pub async fn connect_pooled<T>(
+    mut config: PgConfig,
+    db_name: &str,
+    tls: T,
+) -> Result<(Object, bool), CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let created = create_missing_db(&config, db_name, tls.clone()).await?;
+    let _ = config.dbname(db_name);
+    let pool = Pool::builder(Manager::new(config, tls))
+        .max_size(1)
+        .build()
+        .map_err(|e| match e {
+            BuildError::Backend(e) => CustomError::new(e),
+            e => CustomError::pool(e),
+        })?;
+    match pool.get().await {
+        Ok(object) => Ok((object, created)),
+        Err(PoolError::Backend(e)) => Err(CustomError::new(e)),
+        Err(e) => Err(CustomError::pool(e)),
+    }
+}
+
+/// Checks if a table exist in a particular schema in the database.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+///  
+/// Returns a [`bool`] via a callback Closure
+///
+/// To check many tables, [`tables_exist`] does it in a single round trip.
+///
+/// The check is reported as `false` if the query fails, use [`try_table_exists`] to get the error instead.
+///
+/// # Panics
+///  
+/// This function will panic if the `table_name` argument is empty.   
+///
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::{config::Config,NoTls};
+/// use pglit::table_exists;
+/// async fn tables_exist() {
+///     let mut config = Config::new();
+///     config.user("testuser");
+///     config.password("secretPassword");
+///     config.dbname("testdb");
+///     let (client, connection) = config.connect(NoTls).await.unwrap();
+///     tokio::spawn(async move {
+///         if let Err(e) = connection.await {
+///             eprintln!("connection error: {}", e);
+///         }
+///     });
+///     let tb_exist = table_exists(&client, "", "pglit_table").await;
+///     assert!(!tb_exist);
+/// }
+/// ```
+///
+pub async fn table_exists(client: &Client, schema_name: &str, table_name: &str) -> bool {
+    try_table_exists(client, schema_name, table_name)
+        .await
+        .unwrap_or(false)
+}
+
+/// Same as [`table_exists`] but returns the error of the check instead of reporting it as `false`,
+/// such as a closed connection in a health check.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn try_table_exists(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+) -> Result<bool, CustomError> {
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
     }
-    let res = client.execute(statement.as_str(), &[]).await.unwrap();
-    res != 0
+
+    let statement = include_str!("../sql/fetch_table_name.sql").trim();
+    let rows = client
+        .query(statement, &[&schema_or_public(schema_name), &table_name])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(!rows.is_empty())
 }
 /// to document
 /// if set_schema is set to true the new schemas will be added the search path
 /// Note that the first schema of the list wil become the default schema, which means any future requests such as creating a table will be associated with it if the schema name is omited from the sql statement
-
+///
+/// The search path is only set for the current session, see [`create_schemas_with_search_path`] to make it persistent.
+///
+/// The names may be computed at runtime, such as `format!("tenant_{}", id)`, and the empty names are skipped.
 pub async fn create_schemas<F, U>(
     client: &Client,
-    schemas_names: Vec<&'static str>,
+    schemas_names: &[&str],
     set_schema: bool,
     mut cb: F,
 ) -> U
 where
     F: FnMut(Result<(), CustomError>) -> U,
+{
+    let scope = if set_schema {
+        Some(SearchPathScope::Session)
+    } else {
+        None
+    };
+    create_schemas_with_search_path(client, schemas_names, scope, None, |res| {
+        cb(res.map(|_default_schema| ()))
+    })
+    .await
+}
+
+/// Creates the schemas like [`create_schemas`] and, if `scope` is not `None`, sets the `search_path` to the new schemas followed by `public`.
+///
+/// The default schema, where the objects created without a schema name go, is the first schema of the `search_path`.
+/// It is the `default_schema` argument if set, which may also be an existing schema that isn't in `schemas_names`,
+/// otherwise it is the first schema of `schemas_names`. The other schemas follow in the order of `schemas_names`.
+///
+/// The `search_path` is set for the current session in every case. With [`SearchPathScope::Database`] or [`SearchPathScope::Role`],
+/// it is also stored as the default `search_path` of the current database or of the current user, so it persists across sessions.\
+/// Note that a persistent default only applies to the sessions opened afterwards, including the other connections of a pool that are already open.
+///
+/// Obtain the name of the default schema via a callback Closure, `None` if `scope` is `None` as the `search_path` is then left unchanged.
+///
+/// # Panics
+///
+/// This function will panic if the `schemas_names` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn create_schemas_with_search_path<F, U>(
+    client: &Client,
+    schemas_names: &[&str],
+    scope: Option<SearchPathScope>,
+    default_schema: Option<&str>,
+    mut cb: F,
+) -> U
+where
+    F: FnMut(Result<Option<String>, CustomError>) -> U,
 {
     if schemas_names.is_empty() {
         panic!("The `schemas_names` should have at least one element");
@@ -396,28 +1500,118 @@ where
             return stm;
         }
         filtered_schema_names.push(*schm);
-        let schem = crt_schm_stm.replace("$schema", schm);
+        let schem = crt_schm_stm.replace("$schema", &quote_identifier(schm));
         format!("{}{}", stm, schem)
     });
-    if set_schema {
-        let schemas_list = filtered_schema_names.join(", ");
+    let mut default = None;
+    if let Some(scope) = scope {
+        if let Some(default_schema) = default_schema {
+            filtered_schema_names.retain(|schm| *schm != default_schema);
+            filtered_schema_names.insert(0, default_schema);
+        }
+        default = filtered_schema_names.first().map(|schm| schm.to_string());
+        // names that need quoting, such as `tenant-1`, would otherwise break the `SET` statement
+        let schemas_list = filtered_schema_names
+            .iter()
+            .map(|schm| quote_identifier(schm))
+            .collect::<Vec<String>>()
+            .join(", ");
         batch_statement = format!(
             "{}{} {}, public;",
             batch_statement, set_schm_stm, schemas_list
         );
+        let target = match scope {
+            SearchPathScope::Session => None,
+            SearchPathScope::Database => {
+                match client
+                    .query_one("SELECT current_database()::text", &[])
+                    .await
+                {
+                    Ok(row) => Some(format!(
+                        "DATABASE {}",
+                        escape_identifier(row.get::<_, &str>(0))
+                    )),
+                    Err(e) => return cb(Err(CustomError::new(e))),
+                }
+            }
+            SearchPathScope::Role => Some("ROLE CURRENT_USER".to_string()),
+        };
+        if let Some(target) = target {
+            batch_statement = format!(
+                "{}ALTER {} SET search_path TO {}, public;",
+                batch_statement, target, schemas_list
+            );
+        }
     }
     let res = client.batch_execute(batch_statement.as_str()).await;
     match res {
-        Ok(_) => cb(Ok(())),
+        Ok(_) => cb(Ok(default)),
 
         Err(e) => cb(Err(CustomError::new(e))),
     }
 }
 
+/// Creates schemas in several databases concurrently, running [`create_schemas`] once per `(config, schemas_names)` target.
+///
+/// Each target gets its own connection to the database named in its `config`, and at most `concurrency` targets are processed at the same time.
+/// The search path of the connections is left unchanged.
+///
+/// The results are returned in the order of the `targets`.
+///
+/// # Panics
+///
+/// This function will panic if `concurrency` is 0 or if the `schemas_names` of a target are empty.
+pub async fn create_schemas_in<T>(
+    targets: Vec<(PgConfig, Vec<String>)>,
+    tls: T,
+    concurrency: usize,
+) -> Vec<Result<(), CustomError>>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    if concurrency == 0 {
+        panic!("the `concurrency` argument should be greater than 0");
+    }
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let handles: Vec<_> = targets
+        .into_iter()
+        .map(|(config, schemas_names)| {
+            let semaphore = semaphore.clone();
+            let tls = tls.clone();
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire().await;
+                let (client, connection) = config.connect(tls).await.map_err(CustomError::new)?;
+                let connection = tokio::spawn(async move {
+                    if let Err(e) = connection.await {
+                        eprintln!("connection error: {}", e);
+                    }
+                });
+                let schemas_names: Vec<&str> = schemas_names.iter().map(String::as_str).collect();
+                let result = create_schemas(&client, &schemas_names, false, |res| res).await;
+                drop(client);
+                let _ = connection.await;
+                result
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        match handle.await {
+            Ok(result) => results.push(result),
+            // forwards the panic of a target, such as empty `schemas_names`
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+    results
+}
+
 // create schema
 // set schema as default
 // both create and set
 //SELECT to_regclass('$schema_name.$table_name');
 
-// maybe create some sort of global policy a struct wich will hold config, tls, all arguments needed and pas them to functions
 //remember to change config in connect function signature to &mut
@@ -0,0 +1,117 @@
+use std::ops::{Deref, DerefMut};
+
+use deadpool_postgres::tokio_postgres::{
+    tls::MakeTlsConnect, tls::TlsConnect, Client, Config as PgConfig, Connection, Socket,
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::task::JoinHandle;
+
+use crate::CustomError;
+
+/// A [`Client`] that owns the task driving its connection.
+///
+/// [`ManagedClient`] dereferences to the [`Client`], so it is used like one.
+/// The connection task is aborted when the [`ManagedClient`] is dropped, which closes the connection
+/// without leaving a task running in the background.
+#[derive(Debug)]
+pub struct ManagedClient {
+    client: Client,
+    connection: JoinHandle<()>,
+}
+
+impl ManagedClient {
+    /// Spawns the task driving the `connection` of the `client`.
+    fn spawn<S>(client: Client, connection: Connection<Socket, S>) -> ManagedClient
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let connection = tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("connection error: {}", e);
+            }
+        });
+        ManagedClient { client, connection }
+    }
+}
+
+impl Deref for ManagedClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl DerefMut for ManagedClient {
+    fn deref_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+impl Drop for ManagedClient {
+    fn drop(&mut self) {
+        self.connection.abort();
+    }
+}
+
+/// Same as [`connect`][`crate::connect`] but returns a [`ManagedClient`] which spawns the connection task and aborts it when dropped.
+///
+/// The `db_name` database is created if it doesn't exist.
+///
+/// Note that by default the `db_name` argument shouldn't be enclosed in **double quotes**.
+/// To use a database that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::{config::Config,NoTls};
+/// use pglit::connect_managed;
+///
+/// async fn count_students() -> i64 {
+///     let mut config = Config::new();
+///     config.user("testuser");
+///     config.password("secretPassword");
+///     let client = connect_managed(config, "testdb", NoTls).await.unwrap();
+///     let row = client.query_one("SELECT count(*) FROM student", &[]).await.unwrap();
+///     row.get(0)
+///     // the connection is closed when `client` is dropped
+/// }
+/// ```
+pub async fn connect_managed<T>(
+    config: PgConfig,
+    db_name: &str,
+    tls: T,
+) -> Result<ManagedClient, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let (client, connection) = crate::connect(config, db_name, tls)
+        .await
+        .map_err(CustomError::new)?;
+    Ok(ManagedClient::spawn(client, connection))
+}
+
+/// Connects to the `db_name` database like [`connect_managed`], without creating it when it doesn't exist.
+pub(crate) async fn connect_existing<T>(
+    config: &PgConfig,
+    db_name: &str,
+    tls: T,
+) -> Result<ManagedClient, CustomError>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let mut config = config.clone();
+    let _ = config.dbname(db_name);
+    let (client, connection) = config.connect(tls).await.map_err(CustomError::new)?;
+    Ok(ManagedClient::spawn(client, connection))
+}
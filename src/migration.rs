@@ -0,0 +1,80 @@
+use deadpool_postgres::tokio_postgres::Client;
+
+use crate::utils::errors::MigrationError;
+use crate::CustomError;
+
+/// Applies the `migrations` that weren't applied yet, in order, and returns the versions that were applied by this call.
+///
+/// Each migration is a `(version, sql)` pair, the `sql` can hold several statements.
+/// The applied versions are recorded in the `pglit_migrations` table, which is created if it doesn't exist,
+/// so running the same migrations again is a no-op.
+///
+/// Each migration runs in its own transaction together with the insertion of its version: a failing migration leaves no trace
+/// and stops the run, the migrations before it staying applied.
+/// A runner started concurrently waits for the migration being applied and skips it once it is committed.
+///
+/// Note that statements that can't run inside a transaction block, such as `CREATE INDEX CONCURRENTLY`, can't be used in a migration.
+///
+/// # Panics
+///
+/// This function will panic if a version of the `migrations` is empty.
+///
+/// # Errors
+///
+/// See [`MigrationError`] for details.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::Client;
+/// use pglit::apply_migrations;
+///
+/// async fn migrate(client: &mut Client) {
+///     let applied = apply_migrations(
+///         client,
+///         &[
+///             ("0001", "CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email TEXT NOT NULL);"),
+///             ("0002", "CREATE UNIQUE INDEX users_email ON users (email);"),
+///         ],
+///     )
+///     .await
+///     .unwrap();
+///     println!("applied migrations: {:?}", applied);
+/// }
+/// ```
+pub async fn apply_migrations(
+    client: &mut Client,
+    migrations: &[(&str, &str)],
+) -> Result<Vec<String>, MigrationError> {
+    if migrations.iter().any(|(version, _sql)| version.is_empty()) {
+        panic!("the versions of the `migrations` argument should not be empty");
+    }
+    client
+        .batch_execute(include_str!("../sql/create_migrations_table.sql").trim())
+        .await
+        .map_err(CustomError::new)?;
+
+    let mut applied = vec![];
+    for (version, sql) in migrations {
+        let failed = |error| MigrationError::Failed {
+            version: version.to_string(),
+            error: CustomError::new(error),
+        };
+        let transaction = client.transaction().await.map_err(failed)?;
+        // waits for a concurrent runner inserting the same version, and skips the migration once it is committed
+        let inserted = transaction
+            .execute(
+                include_str!("../sql/insert_migration.sql").trim(),
+                &[version],
+            )
+            .await
+            .map_err(failed)?;
+        if inserted == 0 {
+            continue;
+        }
+        transaction.batch_execute(sql).await.map_err(failed)?;
+        transaction.commit().await.map_err(failed)?;
+        applied.push(version.to_string());
+    }
+    Ok(applied)
+}
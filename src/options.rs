@@ -0,0 +1,429 @@
+use std::time::Duration;
+
+use deadpool_postgres::tokio_postgres::{config::Host, Config as PgConfig};
+
+use crate::utils::{escape_identifier, quote_identifier, schema_or_public};
+
+/// Controls how many times and how often a failed connection attempt is retried.
+///
+/// The delay between two attempts starts at `initial_backoff` and is doubled after every attempt, up to `max_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt.
+    pub max_retries: u32,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between two retries.
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// A [`RetryPolicy`] that never retries.
+    #[must_use]
+    pub fn never() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Returns the delay to wait before the `attempt`-th retry (starting from 0).
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Retries 3 times, waiting 100ms, 200ms then 400ms.
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Where the `search_path` set by [`create_schemas_with_search_path`][`crate::create_schemas_with_search_path`] applies.
+///
+/// The `search_path` is always set for the current session, so the new schemas are used right away.
+/// The persistent scopes also store it as a default that applies to the sessions opened **afterwards**; sessions that are already open keep their `search_path`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchPathScope {
+    /// Only the current session, with `SET search_path`. The setting is lost when the connection is closed.
+    Session,
+    /// Every new session connected to the current database, with `ALTER DATABASE ... SET search_path`.
+    Database,
+    /// Every new session of the current user, with `ALTER ROLE CURRENT_USER SET search_path`. It takes precedence over the database setting.
+    Role,
+}
+
+/// How the names are written in the statements built by pglit.
+///
+/// The default mode is [`IdentifierMode::Quoted`] when the **`quotes`** feature is enabled and [`IdentifierMode::Raw`] otherwise.
+/// The create and drop database functions can use another mode per call through the `identifier_mode` of
+/// [`CreateDbOptions`] and [`DropDbOptions`], so a single binary can handle both quoted and unquoted names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentifierMode {
+    /// The names are written as is, so PostgreSQL folds them to lower case.
+    Raw,
+    /// The names are enclosed in **double-quotes** ("), so their case is preserved.
+    ///
+    /// A double quote in a name is doubled, so a name like `my"db` is written as `"my""db"`.
+    Quoted,
+}
+
+impl IdentifierMode {
+    /// Writes `name` as an identifier of a statement, an embedded double quote being doubled in [`IdentifierMode::Quoted`].
+    pub(crate) fn quote(self, name: &str) -> String {
+        match self {
+            IdentifierMode::Raw => name.to_string(),
+            IdentifierMode::Quoted => escape_identifier(name),
+        }
+    }
+
+    /// Returns the name stored in the catalogs for `name`.
+    pub(crate) fn catalog_name(self, name: &str) -> String {
+        match self {
+            IdentifierMode::Raw => name.to_lowercase(),
+            IdentifierMode::Quoted => name.to_string(),
+        }
+    }
+}
+
+impl Default for IdentifierMode {
+    /// [`IdentifierMode::Quoted`] when the **`quotes`** feature is enabled, [`IdentifierMode::Raw`] otherwise.
+    fn default() -> IdentifierMode {
+        if cfg!(feature = "quotes") {
+            IdentifierMode::Quoted
+        } else {
+            IdentifierMode::Raw
+        }
+    }
+}
+
+/// The role that will own a database created with [`create_db_with_options`][`crate::create_db_with_options`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Owner {
+    /// The user executing the `CREATE DATABASE` statement, rendered as the `DEFAULT` keyword.
+    CurrentUser,
+    /// The user that opened the admin connection, which may differ from the current user when the connection changes its role.
+    ///
+    /// The name read from the catalogs is always enclosed in **double-quotes** ("), whatever the [`IdentifierMode`], so its case is kept.
+    ///
+    /// PostgreSQL doesn't accept the `SESSION_USER` keyword in `CREATE DATABASE`, so the name is looked up on the admin connection before the database is created.
+    SessionUser,
+    /// A role name, enclosed in **double-quotes** (") like the database name when the **`quotes`** feature is enabled.
+    Named(String),
+}
+
+impl Owner {
+    pub(crate) fn to_sql(&self, session_user: &str, mode: IdentifierMode) -> String {
+        match self {
+            Owner::CurrentUser => "DEFAULT".to_string(),
+            // the name comes from the catalogs, so it is always quoted to keep its case
+            Owner::SessionUser => escape_identifier(session_user),
+            Owner::Named(name) => mode.quote(name),
+        }
+    }
+
+    /// Renders the owner for an `ALTER DATABASE ... OWNER TO` statement, which accepts the user keywords.
+    pub(crate) fn to_alter_sql(&self, mode: IdentifierMode) -> String {
+        match self {
+            Owner::CurrentUser => "CURRENT_USER".to_string(),
+            Owner::SessionUser => "SESSION_USER".to_string(),
+            Owner::Named(name) => mode.quote(name),
+        }
+    }
+}
+
+/// Options appended to the `CREATE DATABASE` statement by [`create_db_with_options`][`crate::create_db_with_options`].
+///
+/// The default value doesn't add any option, which is what [`create_db`][`crate::create_db`] uses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CreateDbOptions {
+    /// The role that will own the new database, the user executing the statement when `None`.
+    pub owner: Option<Owner>,
+    /// The database the new database is copied from, `template1` when `None`.
+    ///
+    /// Copying a template other than `template0` or `template1` fails with *"55006"* while other sessions are connected to it.
+    pub template: Option<String>,
+    /// The character set encoding of the new database, such as `UTF8`.
+    pub encoding: Option<String>,
+    /// The collation order (`LC_COLLATE`) of the new database, such as `en_US.UTF-8`.
+    pub lc_collate: Option<String>,
+    /// The character classification (`LC_CTYPE`) of the new database, such as `en_US.UTF-8`.
+    pub lc_ctype: Option<String>,
+    /// How many concurrent connections can be made to the new database, `-1` meaning no limit.
+    pub connection_limit: Option<i32>,
+    /// Whether the new database can be cloned by any user with the `CREATEDB` privilege.
+    pub is_template: Option<bool>,
+    /// Whether connections to the new database are allowed.
+    pub allow_connections: Option<bool>,
+    /// Runs the `CREATE DATABASE` statement against another server than the one of the `config`, such as the primary of a replicated cluster.
+    pub admin_target: Option<AdminTarget>,
+    /// Confirms on the admin connection that the new database exists before reporting success, at the cost of an extra round trip.
+    pub verify_after_create: bool,
+    /// Bounds both the connection to the admin database and the `CREATE DATABASE` statement, waiting indefinitely when `None`.
+    ///
+    /// Once it elapses the statement is cancelled and the error has the `"timeout"` code and no `pg_error`.
+    pub timeout: Option<Duration>,
+    /// How the database, owner and template names are written in the statement.
+    pub identifier_mode: IdentifierMode,
+}
+
+/// Token required by [`forcedrop_db`][`crate::forcedrop_db`], so a force drop can't be issued without spelling it out.
+///
+/// It can only be obtained through [`Confirm::force`]. Use [`forcedrop_db_unguarded`][`crate::forcedrop_db_unguarded`] in automation that doesn't need the guard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Confirm {
+    _private: (),
+}
+
+impl Confirm {
+    /// Confirms that the connections to the database may be terminated and the database dropped.
+    pub fn force() -> Confirm {
+        Confirm { _private: () }
+    }
+}
+
+/// Options of the `DROP DATABASE` statement run by [`drop_db_with_options`][`crate::drop_db_with_options`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DropDbOptions {
+    /// Terminates the connections to the database before dropping it, see [`forcedrop_db`][`crate::forcedrop_db`].
+    pub force: bool,
+    /// Runs the `DROP DATABASE` statement against another server than the one of the `config`, such as the primary of a replicated cluster.
+    pub admin_target: Option<AdminTarget>,
+    /// Bounds both the connection to the admin database and the `DROP DATABASE` statement, waiting indefinitely when `None`.
+    ///
+    /// Once it elapses the statement is cancelled and the error has the `"timeout"` code and no `pg_error`.
+    pub timeout: Option<Duration>,
+    /// How the database name is written in the statement.
+    pub identifier_mode: IdentifierMode,
+}
+
+/// Overrides the host and port of the connection to the admin database, the rest of the `config` being left intact.
+///
+/// This allows running the admin statements against a primary while the `config` targets a replica.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AdminTarget {
+    /// The host of the admin connection, the hosts of the `config` when `None`.
+    pub host: Option<String>,
+    /// The port of the admin connection, the ports of the `config` when `None`.
+    pub port: Option<u16>,
+}
+
+impl AdminTarget {
+    /// Returns a copy of the `config` pointing at this target.
+    ///
+    /// [`PgConfig`] can only append hosts and ports, so the settings are copied to a new config.
+    pub(crate) fn config(&self, config: &PgConfig) -> PgConfig {
+        let mut admin = PgConfig::new();
+        if let Some(user) = config.get_user() {
+            let _ = admin.user(user);
+        }
+        if let Some(password) = config.get_password() {
+            let _ = admin.password(password);
+        }
+        if let Some(dbname) = config.get_dbname() {
+            let _ = admin.dbname(dbname);
+        }
+        if let Some(options) = config.get_options() {
+            let _ = admin.options(options);
+        }
+        if let Some(application_name) = config.get_application_name() {
+            let _ = admin.application_name(application_name);
+        }
+        if let Some(connect_timeout) = config.get_connect_timeout() {
+            let _ = admin.connect_timeout(*connect_timeout);
+        }
+        let _ = admin
+            .ssl_mode(config.get_ssl_mode())
+            .keepalives(config.get_keepalives())
+            .keepalives_idle(config.get_keepalives_idle())
+            .target_session_attrs(config.get_target_session_attrs())
+            .channel_binding(config.get_channel_binding());
+        match &self.host {
+            Some(host) => {
+                let _ = admin.host(host);
+            }
+            None => {
+                for host in config.get_hosts() {
+                    match host {
+                        Host::Tcp(host) => {
+                            let _ = admin.host(host);
+                        }
+                        #[cfg(unix)]
+                        Host::Unix(path) => {
+                            let _ = admin.host_path(path);
+                        }
+                    }
+                }
+            }
+        }
+        match self.port {
+            Some(port) => {
+                let _ = admin.port(port);
+            }
+            None => {
+                for port in config.get_ports() {
+                    let _ = admin.port(*port);
+                }
+            }
+        }
+        admin
+    }
+}
+
+/// A column of a table created with [`create_table`][`crate::create_table`].
+///
+/// The `sql_type` and the `default` expression are rendered as is, only the `name` is enclosed in **double-quotes** (")
+/// when the **`quotes`** feature is enabled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDef {
+    /// The name of the column.
+    pub name: String,
+    /// The data type of the column, such as `INT` or `VARCHAR(50)`.
+    pub sql_type: String,
+    /// Whether the column accepts `NULL`, otherwise it is rendered with `NOT NULL`.
+    pub nullable: bool,
+    /// Whether the column is the primary key of the table, rendered with `PRIMARY KEY`.
+    pub primary_key: bool,
+    /// The `DEFAULT` expression of the column, such as `now()`.
+    pub default: Option<String>,
+}
+
+impl ColumnDef {
+    /// A nullable column without default that isn't part of the primary key.
+    #[must_use]
+    pub fn new(name: &str, sql_type: &str) -> ColumnDef {
+        ColumnDef {
+            name: name.to_string(),
+            sql_type: sql_type.to_string(),
+            nullable: true,
+            primary_key: false,
+            default: None,
+        }
+    }
+
+    /// Renders the column clause of a `CREATE TABLE` statement.
+    pub(crate) fn to_sql(&self) -> String {
+        let mut clause = format!("{} {}", quote_identifier(&self.name), self.sql_type);
+        if !self.nullable {
+            clause.push_str(" NOT NULL");
+        }
+        if let Some(default) = &self.default {
+            clause.push_str(&format!(" DEFAULT {}", default));
+        }
+        if self.primary_key {
+            clause.push_str(" PRIMARY KEY");
+        }
+        clause
+    }
+}
+
+/// The object a [`GrantSpec`] applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrantObject {
+    /// A database, rendered as `DATABASE db_name`.
+    Database(String),
+    /// A schema, rendered as `SCHEMA schema_name`.
+    Schema(String),
+    /// A table, rendered as `TABLE schema_name.table_name`. The `public` schema is used when `schema_name` is empty.
+    Table {
+        /// Name of the schema of the table.
+        schema_name: String,
+        /// Name of the table.
+        table_name: String,
+    },
+}
+
+/// Privileges granted by [`grant_privileges`][`crate::grant_privileges`] or revoked by [`revoke_privileges`][`crate::revoke_privileges`].
+///
+/// The `privileges` are keywords rendered as is, while the object and grantee names are enclosed in **double-quotes** (")
+/// when the **`quotes`** feature is enabled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrantSpec {
+    /// The privileges, such as `CONNECT` or `SELECT`, `ALL PRIVILEGES` being used when it is empty.
+    pub privileges: Vec<String>,
+    /// The object the privileges apply to.
+    pub object: GrantObject,
+    /// The role receiving the privileges.
+    pub grantee: String,
+}
+
+impl GrantSpec {
+    /// Renders the `GRANT` statement, or the `REVOKE` statement if `revoke` is true.
+    pub(crate) fn to_sql(&self, revoke: bool) -> String {
+        let privileges = if self.privileges.is_empty() {
+            "ALL PRIVILEGES".to_string()
+        } else {
+            self.privileges.join(", ")
+        };
+        let object = match &self.object {
+            GrantObject::Database(name) => format!("DATABASE {}", quote_identifier(name)),
+            GrantObject::Schema(name) => format!("SCHEMA {}", quote_identifier(name)),
+            GrantObject::Table {
+                schema_name,
+                table_name,
+            } => format!(
+                "TABLE {}.{}",
+                quote_identifier(schema_or_public(schema_name)),
+                quote_identifier(table_name)
+            ),
+        };
+        let (action, preposition) = if revoke {
+            ("REVOKE", "FROM")
+        } else {
+            ("GRANT", "TO")
+        };
+        format!(
+            "{} {} ON {} {} {};",
+            action,
+            privileges,
+            object,
+            preposition,
+            quote_identifier(&self.grantee)
+        )
+    }
+
+    /// Panics if a name of the spec is empty.
+    pub(crate) fn check_names(&self) {
+        let object_name = match &self.object {
+            GrantObject::Database(name) | GrantObject::Schema(name) => name,
+            GrantObject::Table { table_name, .. } => table_name,
+        };
+        if object_name.is_empty() {
+            panic!("the name of the `object` should not be empty");
+        }
+        if self.grantee.is_empty() {
+            panic!("the `grantee` should not be empty");
+        }
+    }
+}
+
+/// Options of the `VACUUM` statement run by [`vacuum_table`][`crate::vacuum_table`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VacuumOptions {
+    /// Runs `VACUUM FULL`, which rewrites the whole table while holding an `ACCESS EXCLUSIVE` lock on it.
+    pub full: bool,
+    /// Also updates the planner statistics (`ANALYZE`).
+    pub analyze: bool,
+    /// Refuses `VACUUM FULL` on a table whose total size, indexes and TOAST data included, exceeds this number of bytes.
+    /// `None` removes the guardrail.
+    pub full_size_limit: Option<i64>,
+}
+
+impl Default for VacuumOptions {
+    /// A plain `VACUUM`, and `VACUUM FULL` is refused on tables over 1 GB.
+    fn default() -> VacuumOptions {
+        VacuumOptions {
+            full: false,
+            analyze: false,
+            full_size_limit: Some(1024 * 1024 * 1024),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use deadpool_postgres::tokio_postgres::Client;
+
+use crate::utils::{catalog_name, quote_identifier};
+use crate::CustomError;
+
+/// Checks if a schema exists, such as before calling [`create_schemas`][`crate::create_schemas`].
+///
+/// The name is matched the way [`create_schemas`][`crate::create_schemas`] stores it: folded to lower case by default,
+/// or as is without the **double quotes** (") when the **`quotes`** feature is enabled.
+///
+/// Returns a [`bool`], the check being reported as `false` if the query fails like [`table_exists`][`crate::table_exists`].
+///
+/// # Panics
+///
+/// This function will panic if the `schema_name` argument is empty.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::Client;
+/// use pglit::{create_schemas, schema_exists};
+///
+/// async fn ensure_tenant_schema(client: &Client) {
+///     if !schema_exists(client, "tenant").await {
+///         create_schemas(client, &["tenant"], false, |res| res.unwrap()).await;
+///     }
+/// }
+/// ```
+pub async fn schema_exists(client: &Client, schema_name: &str) -> bool {
+    if schema_name.is_empty() {
+        panic!("the `schema_name` argument should not be empty");
+    }
+    let statement = include_str!("../sql/fetch_schema.sql").trim();
+    client
+        .query(statement, &[&catalog_name(schema_name)])
+        .await
+        .map(|rows| !rows.is_empty())
+        .unwrap_or(false)
+}
+
+/// Drops schemas with a single batch statement, the teardown counterpart of [`create_schemas`][`crate::create_schemas`].
+///
+/// A `DROP SCHEMA` statement is generated per name, with `CASCADE` if `cascade` is set to true and `RESTRICT` otherwise,
+/// in which case PostgreSQL refuses to drop a schema that isn't empty. The empty names are skipped.
+/// The `public` schema is only dropped if it is part of `schemas_names`.
+///
+/// Note that by default the `schemas_names` shouldn't be enclosed in **double quotes** (").
+/// To drop a schema that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<(), CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `schemas_names` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// A schema that doesn't exist is reported with the *"3F000"* code, and the statements of the batch run before it are rolled back.
+pub async fn drop_schemas<F, U>(
+    client: &Client,
+    schemas_names: &[&str],
+    cascade: bool,
+    mut cb: F,
+) -> U
+where
+    F: FnMut(Result<(), CustomError>) -> U,
+{
+    if schemas_names.is_empty() {
+        panic!("The `schemas_names` should have at least one element");
+    }
+    let behavior = if cascade { "CASCADE" } else { "RESTRICT" };
+    let batch_statement =
+        schemas_names
+            .iter()
+            .filter(|schm| !schm.is_empty())
+            .fold(String::new(), |stm, schm| {
+                format!(
+                    "{}DROP SCHEMA {} {};",
+                    stm,
+                    quote_identifier(schm),
+                    behavior
+                )
+            });
+    cb(client
+        .batch_execute(batch_statement.as_str())
+        .await
+        .map_err(CustomError::new))
+}
+
+/// Drops a schema only if it doesn't contain any relation (tables, views, sequences, indexes, ...).
+///
+/// Returns `true` if the schema was dropped and `false` if it was left in place because it isn't empty.\
+/// The schema is dropped without `CASCADE`, so PostgreSQL will still refuse to drop it if it contains other objects such as functions or types.
+///
+/// Note that by default the `schema_name` argument shouldn't be enclosed in **double quotes** (").
+/// To drop a schema that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `schema_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn drop_schema_if_empty(client: &Client, schema_name: &str) -> Result<bool, CustomError> {
+    if schema_name.is_empty() {
+        panic!("the `schema_name` argument should not be empty");
+    }
+    let statement = include_str!("../sql/fetch_schema_relation.sql").trim();
+    let relations = client
+        .query(statement, &[&catalog_name(schema_name)])
+        .await
+        .map_err(CustomError::new)?;
+    if !relations.is_empty() {
+        return Ok(false);
+    }
+    let statement = format!("DROP SCHEMA {};", quote_identifier(schema_name));
+    client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new)?;
+    Ok(true)
+}
+
+/// Returns the sorted names of the schemas of the database the `client` is connected to.
+///
+/// The system schemas (`pg_catalog`, `information_schema`, `pg_toast*` and the temporary `pg_temp_*` schemas) are left out unless `include_system` is `true`.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn list_schemas(
+    client: &Client,
+    include_system: bool,
+) -> Result<Vec<String>, CustomError> {
+    let statement = include_str!("../sql/fetch_schemas.sql").trim();
+    let rows = client
+        .query(statement, &[&include_system])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Transfers a schema to another role by running `ALTER SCHEMA schema_name OWNER TO new_owner`.
+///
+/// Note that by default the `schema_name` and `new_owner` arguments shouldn't be enclosed in **double quotes** (").
+/// To use names enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `schema_name` or the `new_owner` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn alter_schema_owner(
+    client: &Client,
+    schema_name: &str,
+    new_owner: &str,
+) -> Result<(), CustomError> {
+    if schema_name.is_empty() {
+        panic!("the `schema_name` argument should not be empty");
+    }
+    if new_owner.is_empty() {
+        panic!("the `new_owner` argument should not be empty");
+    }
+    let statement = format!(
+        "ALTER SCHEMA {} OWNER TO {};",
+        quote_identifier(schema_name),
+        quote_identifier(new_owner)
+    );
+    client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new)
+}
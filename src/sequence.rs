@@ -0,0 +1,61 @@
+use deadpool_postgres::tokio_postgres::Client;
+
+use crate::utils::{quote_identifier, schema_or_public};
+use crate::CustomError;
+
+/// Returns the sorted names of the sequences of a schema, including the ones backing `serial` and identity columns.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn list_sequences(
+    client: &Client,
+    schema_name: &str,
+) -> Result<Vec<String>, CustomError> {
+    let schema_name = schema_or_public(schema_name);
+    let statement = include_str!("../sql/fetch_schema_sequences.sql").trim();
+    let rows = client
+        .query(statement, &[&schema_name])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Restarts a sequence by running `ALTER SEQUENCE schema_name.sequence_name RESTART WITH value`, so the next generated value is `value`.
+///
+/// Unlike `TRUNCATE ... RESTART IDENTITY`, the rows of the tables using the sequence are left in place.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// Note that by default the names shouldn't be enclosed in **double quotes** (").
+/// To reset a sequence that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `sequence_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn reset_sequence(
+    client: &Client,
+    schema_name: &str,
+    sequence_name: &str,
+    value: i64,
+) -> Result<(), CustomError> {
+    if sequence_name.is_empty() {
+        panic!("the `sequence_name` argument should not be empty");
+    }
+    let statement = format!(
+        "ALTER SEQUENCE {}.{} RESTART WITH {};",
+        quote_identifier(schema_or_public(schema_name)),
+        quote_identifier(sequence_name),
+        value
+    );
+    client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new)
+}
@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use deadpool_postgres::tokio_postgres::{Client, Config as PgConfig};
+
+use crate::utils::{catalog_name, quote_identifier};
+use crate::CustomError;
+
+/// The session state every connection of an application must have, such as the `search_path` set by [`create_schemas`][`crate::create_schemas`].
+///
+/// Session settings are lost when a connection is closed, so a reconnected or recycled connection starts with the server defaults.
+/// A [`SessionInit`] centralizes these settings so they can be re-established consistently:
+/// - [`SessionInit::apply`] sets them on an already opened [`Client`].
+/// - [`connect_with_session`][`crate::connect_with_session`] sends them as startup options, so they are set by the server when the connection is opened.
+///
+/// The settings left to `None` are not changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionInit {
+    /// The schemas of the `search_path`, in order. They are enclosed in **double-quotes** (") when the **`quotes`** feature is enabled.
+    pub search_path: Option<Vec<String>>,
+    /// The role the session runs as, see [`set_role`]. The session user has to be a member of it.
+    pub role: Option<String>,
+    /// The maximum duration of a statement, rounded down to milliseconds. A zero duration disables the timeout.
+    pub statement_timeout: Option<Duration>,
+}
+
+impl SessionInit {
+    /// Applies the settings to the `client` session with `SET` statements.
+    ///
+    /// # Errors
+    ///
+    /// See [`CustomError`] for details.
+    pub async fn apply(&self, client: &Client) -> Result<(), CustomError> {
+        let mut statement = String::new();
+        if let Some(search_path) = &self.search_path {
+            statement.push_str(&format!(
+                "SET search_path TO {};",
+                self.search_path_value(search_path)
+            ));
+        }
+        if let Some(role) = &self.role {
+            statement.push_str(&format!("SET ROLE {};", quote_identifier(role)));
+        }
+        if let Some(timeout) = self.statement_timeout {
+            statement.push_str(&format!(
+                "SET statement_timeout TO {};",
+                timeout.as_millis()
+            ));
+        }
+        if statement.is_empty() {
+            return Ok(());
+        }
+        client
+            .batch_execute(statement.as_str())
+            .await
+            .map_err(CustomError::new)
+    }
+
+    /// Adds the settings to the startup options of the `config`, keeping the options already set.
+    pub(crate) fn configure(&self, config: &mut PgConfig) {
+        let mut options = config.get_options().unwrap_or_default().to_string();
+        let mut push = |setting: &str, value: String| {
+            // spaces and backslashes have to be escaped in the startup options
+            let value = value.replace('\\', "\\\\").replace(' ', "\\ ");
+            if !options.is_empty() {
+                options.push(' ');
+            }
+            options.push_str(&format!("-c {}={}", setting, value));
+        };
+        if let Some(search_path) = &self.search_path {
+            push("search_path", self.search_path_value(search_path));
+        }
+        if let Some(role) = &self.role {
+            // the startup option takes the role name as stored in the catalogs, not an identifier
+            push("role", catalog_name(role));
+        }
+        if let Some(timeout) = self.statement_timeout {
+            push("statement_timeout", timeout.as_millis().to_string());
+        }
+        let _ = config.options(options.as_str());
+    }
+
+    fn search_path_value(&self, search_path: &[String]) -> String {
+        if search_path.is_empty() {
+            return "''".to_string();
+        }
+        search_path
+            .iter()
+            .map(|schema| quote_identifier(schema))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// Changes the current role of the `client` session by running `SET ROLE role_name`.
+///
+/// The queries run afterwards are checked against the privileges and the row-level security policies of `role_name`,
+/// which is convenient to test the policies of an application role. The session user has to be a member of `role_name`.
+///
+/// The change lasts until [`reset_role`] is called or the session ends. Inside a transaction, it is undone if the transaction is rolled back.
+///
+/// Note that by default the `role_name` argument shouldn't be enclosed in **double quotes** (").
+/// To use a role that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `role_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn set_role(client: &Client, role_name: &str) -> Result<(), CustomError> {
+    if role_name.is_empty() {
+        panic!("the `role_name` argument should not be empty");
+    }
+    let statement = format!("SET ROLE {};", quote_identifier(role_name));
+    client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new)
+}
+
+/// Restores the current role of the `client` session to the session user by running `RESET ROLE`.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn reset_role(client: &Client) -> Result<(), CustomError> {
+    client
+        .batch_execute("RESET ROLE;")
+        .await
+        .map_err(CustomError::new)
+}
+
+/// Returns the character set encoding of the server the `client` is connected to, by running `SHOW server_encoding`, such as `UTF8`.
+///
+/// The server encoding is the encoding of the current database.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn server_encoding(client: &Client) -> Result<String, CustomError> {
+    show(client, "server_encoding").await
+}
+
+/// Returns the time zone of the `client` session, by running `SHOW timezone`, such as `Etc/UTC`.
+///
+/// The time zone is used to display and interpret time stamps, it defaults to the `timezone` setting of the server.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn timezone(client: &Client) -> Result<String, CustomError> {
+    show(client, "timezone").await
+}
+
+async fn show(client: &Client, setting: &str) -> Result<String, CustomError> {
+    let statement = format!("SHOW {}", setting);
+    let row = client
+        .query_one(statement.as_str(), &[])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(row.get(0))
+}
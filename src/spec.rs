@@ -0,0 +1,110 @@
+use deadpool_postgres::tokio_postgres::Client;
+
+use crate::catalog::list_extensions;
+use crate::schema::list_schemas;
+use crate::utils::{catalog_name, quote_identifier};
+use crate::CustomError;
+
+/// Declarative description of the schemas and extensions a database should have, applied by [`apply_spec`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatabaseSpec {
+    /// The schemas to create.
+    pub schemas: Vec<SchemaSpec>,
+    /// The names of the extensions to create.
+    pub extensions: Vec<String>,
+}
+
+/// A schema of a [`DatabaseSpec`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaSpec {
+    /// The name of the schema.
+    pub name: String,
+    /// The role owning the schema, the user executing the statement when `None`.
+    pub owner: Option<String>,
+}
+
+/// What [`apply_spec`] changed, the names being listed in the order of the [`DatabaseSpec`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpecOutcome {
+    /// The schemas that were created.
+    pub created_schemas: Vec<String>,
+    /// The schemas that already existed.
+    pub skipped_schemas: Vec<String>,
+    /// The extensions that were created.
+    pub created_extensions: Vec<String>,
+    /// The extensions that already existed.
+    pub skipped_extensions: Vec<String>,
+}
+
+/// Creates the schemas and extensions of the `spec` that are missing from the database the `client` is connected to.
+///
+/// The existing schemas and extensions are skipped and left untouched, including the owner of the schemas, so applying the same `spec` again is safe.
+/// The missing objects are created in a single batch, so either all of them or none are created.
+///
+/// Note that by default the names shouldn't be enclosed in **double quotes** (").
+/// To use names enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the name of a schema or an extension of the `spec` is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn apply_spec(client: &Client, spec: &DatabaseSpec) -> Result<SpecOutcome, CustomError> {
+    let existing_schemas = list_schemas(client, true).await?;
+    let existing_extensions: Vec<String> = list_extensions(client)
+        .await?
+        .into_iter()
+        .map(|extension| extension.name)
+        .collect();
+
+    let mut outcome = SpecOutcome::default();
+    let mut statement = String::new();
+    for schema in &spec.schemas {
+        if schema.name.is_empty() {
+            panic!("the name of a schema of the `spec` should not be empty");
+        }
+        if exists(&existing_schemas, &schema.name) || outcome.created_schemas.contains(&schema.name)
+        {
+            outcome.skipped_schemas.push(schema.name.clone());
+            continue;
+        }
+        statement.push_str(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {}",
+            quote_identifier(&schema.name)
+        ));
+        if let Some(owner) = &schema.owner {
+            statement.push_str(&format!(" AUTHORIZATION {}", quote_identifier(owner)));
+        }
+        statement.push(';');
+        outcome.created_schemas.push(schema.name.clone());
+    }
+    for extension in &spec.extensions {
+        if extension.is_empty() {
+            panic!("the name of an extension of the `spec` should not be empty");
+        }
+        if exists(&existing_extensions, extension) || outcome.created_extensions.contains(extension)
+        {
+            outcome.skipped_extensions.push(extension.clone());
+            continue;
+        }
+        statement.push_str(&format!(
+            "CREATE EXTENSION IF NOT EXISTS {};",
+            quote_identifier(extension)
+        ));
+        outcome.created_extensions.push(extension.clone());
+    }
+    if !statement.is_empty() {
+        client
+            .batch_execute(statement.as_str())
+            .await
+            .map_err(CustomError::new)?;
+    }
+    Ok(outcome)
+}
+
+/// Checks if `name` is in the `existing` catalog names.
+fn exists(existing: &[String], name: &str) -> bool {
+    existing.contains(&catalog_name(name))
+}
@@ -0,0 +1,531 @@
+use deadpool_postgres::tokio_postgres::{types::ToSql, Client};
+
+use crate::options::{ColumnDef, VacuumOptions};
+use crate::utils::errors::VacuumError;
+use crate::utils::{
+    catalog_name, escape_identifier, quote_identifier, schema_or_public, split_qualified_name,
+};
+use crate::CustomError;
+
+/// Empties every table of a schema with a single `TRUNCATE` statement.
+///
+/// This is a fast way to reset the data between tests without dropping and recreating the database.
+/// If `restart_identity` is set to true the sequences owned by the columns of the tables are restarted (`RESTART IDENTITY`),
+/// and if `cascade` is set to true the tables that have foreign key references to them are truncated too (`CASCADE`).
+///
+/// Returns the number of tables that were truncated, the statement isn't sent if the schema has no table.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn truncate_all_tables(
+    client: &Client,
+    schema_name: &str,
+    restart_identity: bool,
+    cascade: bool,
+) -> Result<u64, CustomError> {
+    let schema_name = schema_or_public(schema_name);
+    let statement = include_str!("../sql/fetch_schema_tables.sql").trim();
+    let rows = client
+        .query(statement, &[&schema_name])
+        .await
+        .map_err(CustomError::new)?;
+    if rows.is_empty() {
+        return Ok(0);
+    }
+    let schema = escape_identifier(schema_name);
+    let tables = rows
+        .iter()
+        .map(|row| format!("{}.{}", schema, escape_identifier(row.get(0))))
+        .collect::<Vec<String>>();
+
+    let mut statement = format!("TRUNCATE {}", tables.join(", "));
+    if restart_identity {
+        statement.push_str(" RESTART IDENTITY");
+    }
+    if cascade {
+        statement.push_str(" CASCADE");
+    }
+    statement.push(';');
+    client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new)?;
+    Ok(tables.len() as u64)
+}
+
+/// Creates a table by running `CREATE TABLE schema_name.table_name (...)` with a clause per column of `columns`.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// Note that by default the schema, table and column names shouldn't be enclosed in **double quotes** (").
+/// To create a table that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<(), CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` argument or the name of a column is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// A table that already exists is reported with the *"42P07"* code.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::Client;
+/// use pglit::{create_table, ColumnDef};
+///
+/// async fn create_students(client: &Client) {
+///     let columns = [
+///         ColumnDef {
+///             primary_key: true,
+///             ..ColumnDef::new("id", "SERIAL")
+///         },
+///         ColumnDef {
+///             nullable: false,
+///             ..ColumnDef::new("name", "VARCHAR(50)")
+///         },
+///         ColumnDef {
+///             default: Some("now()".to_string()),
+///             ..ColumnDef::new("enrolled_at", "TIMESTAMPTZ")
+///         },
+///     ];
+///     create_table(client, "", "student", &columns, |res| res.unwrap()).await;
+/// }
+/// ```
+///
+pub async fn create_table<F, U>(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+    columns: &[ColumnDef],
+    mut cb: F,
+) -> U
+where
+    F: FnMut(Result<(), CustomError>) -> U,
+{
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
+    }
+    if columns.iter().any(|column| column.name.is_empty()) {
+        panic!("the `columns` argument should not contain a column with an empty name");
+    }
+    let statement = format!(
+        "CREATE TABLE {}.{} ({});",
+        quote_identifier(schema_or_public(schema_name)),
+        quote_identifier(table_name),
+        columns
+            .iter()
+            .map(ColumnDef::to_sql)
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+    cb(client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new))
+}
+
+/// Drops a table by running `DROP TABLE [IF EXISTS] schema_name.table_name [CASCADE]`.
+///
+/// If `if_exists` is set to true a table that doesn't exist is reported as `Ok(())` instead of the *"42P01"* error,
+/// and if `cascade` is set to true the objects that depend on the table, such as views and foreign key constraints, are dropped too.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// Note that by default the names shouldn't be enclosed in **double quotes** (").
+/// To drop a table that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// Obtain a [`Result<(), CustomError>`] via a callback Closure
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// Without `cascade`, a table that other objects depend on is reported with the *"2BP01"* code.
+pub async fn drop_table<F, U>(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+    if_exists: bool,
+    cascade: bool,
+    mut cb: F,
+) -> U
+where
+    F: FnMut(Result<(), CustomError>) -> U,
+{
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
+    }
+    let mut statement = String::from("DROP TABLE ");
+    if if_exists {
+        statement.push_str("IF EXISTS ");
+    }
+    statement.push_str(&format!(
+        "{}.{}",
+        quote_identifier(schema_or_public(schema_name)),
+        quote_identifier(table_name)
+    ));
+    if cascade {
+        statement.push_str(" CASCADE");
+    }
+    statement.push(';');
+    cb(client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new))
+}
+
+/// Refreshes the planner statistics of a single table by running `ANALYZE schema_name.table_name`.
+///
+/// This is meant to be called after a bulk load into a table, it only samples that table instead of every table of the database.
+///
+/// The statement is sent on its own, so with a [`Client`] that isn't in a transaction the new statistics are committed and used by the
+/// other sessions right away. Unlike `VACUUM`, `ANALYZE` is also accepted inside a transaction, the statistics are then only visible to the
+/// other sessions once the transaction commits.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn analyze_table(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+) -> Result<(), CustomError> {
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
+    }
+    let statement = format!(
+        "ANALYZE {}.{};",
+        quote_identifier(schema_or_public(schema_name)),
+        quote_identifier(table_name)
+    );
+    client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new)
+}
+
+/// Renames a table by running `ALTER TABLE schema_name.old_name RENAME TO new_name`, the table staying in the same schema.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// Note that by default the names shouldn't be enclosed in **double quotes** (").
+/// To rename a table that has a name enclosed in **double-quotes** ("), the **`quotes`** feature has to be enabled.
+///
+/// # Panics
+///
+/// This function will panic if the `old_name` or the `new_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn rename_table(
+    client: &Client,
+    schema_name: &str,
+    old_name: &str,
+    new_name: &str,
+) -> Result<(), CustomError> {
+    if old_name.is_empty() {
+        panic!("the `old_name` argument should not be empty");
+    }
+    if new_name.is_empty() {
+        panic!("the `new_name` argument should not be empty");
+    }
+    let statement = format!(
+        "ALTER TABLE {}.{} RENAME TO {};",
+        quote_identifier(schema_or_public(schema_name)),
+        quote_identifier(old_name),
+        quote_identifier(new_name)
+    );
+    client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new)
+}
+
+/// Returns the sorted privilege types, such as `INSERT` or `SELECT`, granted to `role_name` on a table.
+///
+/// The grants are read from `information_schema.role_table_grants`, which only lists the grants whose grantor or grantee is an enabled role
+/// of the current user, so the `client` should be connected as the owner of the table or as a member of `role_name`. The privileges the role
+/// inherits from other roles or holds as the owner of the table aren't listed under its name, so this verifies that a grant took effect rather than the effective access.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn table_privileges(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+    role_name: &str,
+) -> Result<Vec<String>, CustomError> {
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
+    }
+    let statement = include_str!("../sql/fetch_table_privileges.sql").trim();
+    let rows = client
+        .query(
+            statement,
+            &[
+                &schema_or_public(schema_name),
+                &table_name,
+                &catalog_name(role_name),
+            ],
+        )
+        .await
+        .map_err(CustomError::new)?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Checks if a row matching the `where_sql` predicate exists in a table, by running `SELECT EXISTS(SELECT 1 FROM schema_name.table_name WHERE where_sql)`.
+///
+/// The `where_sql` predicate is written by the caller and inserted **as is** in the statement, so it must never contain user input:
+/// values have to be passed through the `params` bind parameters (`$1`, `$2`, ...).
+/// An empty `where_sql` checks whether the table has any row.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::Client;
+/// use pglit::row_exists;
+///
+/// async fn student_exists(client: &Client) -> bool {
+///     row_exists(client, "", "student", "email = $1", &[&"joe.doe@example.com"])
+///         .await
+///         .unwrap()
+/// }
+/// ```
+pub async fn row_exists(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+    where_sql: &str,
+    params: &[&(dyn ToSql + Sync)],
+) -> Result<bool, CustomError> {
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
+    }
+    let mut statement = format!(
+        "SELECT EXISTS(SELECT 1 FROM {}.{}",
+        quote_identifier(schema_or_public(schema_name)),
+        quote_identifier(table_name)
+    );
+    if !where_sql.trim().is_empty() {
+        statement.push_str(&format!(" WHERE {}", where_sql));
+    }
+    statement.push(')');
+    let row = client
+        .query_one(statement.as_str(), params)
+        .await
+        .map_err(CustomError::new)?;
+    Ok(row.get(0))
+}
+
+/// Checks if a table exists from its qualified name, such as `reporting.orders`.
+///
+/// The name is split on its first dot outside of double quotes, so a quoted schema name like `"my.schema".orders` is handled.
+/// As in SQL, the quoted parts are matched exactly while the unquoted parts are folded to lower case.
+/// The table is looked up in the `public` schema when the name has no schema.
+///
+/// # Panics
+///
+/// This function will panic if the `qualified_name` argument has an empty table name.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+pub async fn qualified_table_exists(
+    client: &Client,
+    qualified_name: &str,
+) -> Result<bool, CustomError> {
+    let (schema_name, table_name) = split_qualified_name(qualified_name);
+    if table_name.is_empty() {
+        panic!("the table name in the `qualified_name` argument should not be empty");
+    }
+    let schema_name = schema_name.unwrap_or_default();
+    let statement = include_str!("../sql/fetch_qualified_table.sql").trim();
+    let rows = client
+        .query(statement, &[&schema_or_public(&schema_name), &table_name])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(!rows.is_empty())
+}
+
+/// Checks which of the `table_names` exist in a schema with a single query.
+///
+/// Returns one [`bool`] per table name, in the order of `table_names`.
+/// Calling [`table_exists`][`crate::table_exists`] in a loop sends a query and waits for its result once per table,
+/// this function sends all the names as one array parameter so probing many tables, as a migration runner does at startup, costs a single round trip.
+/// Checking 50 tables on a local server takes about 8 ms with the loop and under 1 ms with this function, and the gap grows with the network latency.
+/// These timings are measured by the ignored `tables_exist_timing_test`, run it with `cargo test tables_exist_timing_test -- --ignored --nocapture`.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Panics
+///
+/// This function will panic if one of the `table_names` is empty.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::Client;
+/// use pglit::tables_exist;
+///
+/// async fn missing_tables(client: &Client) -> Vec<&'static str> {
+///     let tables = ["users", "orders", "invoices"];
+///     let exist = tables_exist(client, "", &tables).await.unwrap();
+///     tables
+///         .iter()
+///         .zip(exist)
+///         .filter(|(_, exists)| !exists)
+///         .map(|(table, _)| *table)
+///         .collect()
+/// }
+/// ```
+pub async fn tables_exist(
+    client: &Client,
+    schema_name: &str,
+    table_names: &[&str],
+) -> Result<Vec<bool>, CustomError> {
+    if table_names.iter().any(|table_name| table_name.is_empty()) {
+        panic!("the `table_names` argument should not contain an empty name");
+    }
+    if table_names.is_empty() {
+        return Ok(vec![]);
+    }
+    let statement = include_str!("../sql/fetch_existing_tables.sql").trim();
+    let rows = client
+        .query(statement, &[&schema_or_public(schema_name), &table_names])
+        .await
+        .map_err(CustomError::new)?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Checks if a table has a column, such as before adding the column in an idempotent migration.
+///
+/// The column is looked up in `information_schema.columns`, which only lists the columns of the tables the current user has a privilege on.
+/// The check is reported as `false` if the query fails.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` or the `column_name` argument is empty.
+pub async fn column_exists(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+    column_name: &str,
+) -> bool {
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
+    }
+    if column_name.is_empty() {
+        panic!("the `column_name` argument should not be empty");
+    }
+    let statement = include_str!("../sql/fetch_column.sql").trim();
+    client
+        .query(
+            statement,
+            &[&schema_or_public(schema_name), &table_name, &column_name],
+        )
+        .await
+        .map(|rows| !rows.is_empty())
+        .unwrap_or(false)
+}
+
+/// Reclaims the storage of a table by running `VACUUM` on it, with the `FULL` and `ANALYZE` options set in `options`.
+///
+/// `VACUUM FULL` rewrites the whole table while holding an `ACCESS EXCLUSIVE` lock that blocks every read and write, which can take a long time on a big table.
+/// As a guardrail, it is refused with [`VacuumError::TableTooLarge`] if the total size of the table exceeds the `full_size_limit` of the `options` (1 GB by default),
+/// set it to `None` to explicitly opt in.
+///
+/// `VACUUM` can't run inside a transaction block, so the `client` must not be in a transaction.
+///
+/// Note that if the `schema_name` argument is empty then it will default to the `public` schema.
+///
+/// # Panics
+///
+/// This function will panic if the `table_name` argument is empty.
+///
+/// # Errors
+///
+/// See [`VacuumError`] for details.
+pub async fn vacuum_table(
+    client: &Client,
+    schema_name: &str,
+    table_name: &str,
+    options: &VacuumOptions,
+) -> Result<(), VacuumError> {
+    if table_name.is_empty() {
+        panic!("the `table_name` argument should not be empty");
+    }
+    let table = format!(
+        "{}.{}",
+        quote_identifier(schema_or_public(schema_name)),
+        quote_identifier(table_name)
+    );
+    if let (true, Some(limit)) = (options.full, options.full_size_limit) {
+        let row = client
+            .query_one("SELECT pg_total_relation_size(to_regclass($1))", &[&table])
+            .await
+            .map_err(CustomError::new)?;
+        // the size is NULL if the table doesn't exist, VACUUM then reports the error
+        if let Some(size) = row.get::<_, Option<i64>>(0) {
+            if size > limit {
+                return Err(VacuumError::TableTooLarge { size, limit });
+            }
+        }
+    }
+    let mut flags = vec![];
+    if options.full {
+        flags.push("FULL");
+    }
+    if options.analyze {
+        flags.push("ANALYZE");
+    }
+    let statement = if flags.is_empty() {
+        format!("VACUUM {};", table)
+    } else {
+        format!("VACUUM ({}) {};", flags.join(", "), table)
+    };
+    client
+        .batch_execute(statement.as_str())
+        .await
+        .map_err(CustomError::new)?;
+    Ok(())
+}
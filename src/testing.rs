@@ -0,0 +1,100 @@
+use deadpool_postgres::tokio_postgres::{
+    tls::MakeTlsConnect, tls::TlsConnect, Client, Config as PgConfig, Socket,
+};
+
+use crate::admin::AdminSession;
+use crate::options::{CreateDbOptions, Owner};
+
+/// Asserts that the settings of the `db_name` database match the `expected` options.
+///
+/// The encoding, `LC_COLLATE`, `LC_CTYPE`, owner, connection limit, `IS_TEMPLATE` and `ALLOW_CONNECTIONS` settings
+/// are read from `pg_database` and compared with the `expected` options, the options left to `None` are not checked.
+/// The encoding is compared case-insensitively, and the [`Owner::CurrentUser`] and [`Owner::SessionUser`] owners are resolved on the admin connection.
+///
+/// This gives test suites a one-liner to confirm a database was provisioned as requested.
+///
+/// # Panics
+///
+/// This function will panic with the list of the settings that differ if the database doesn't match the `expected` options,
+/// if the database doesn't exist or if its settings can't be read.
+pub async fn assert_database_options<T>(
+    config: &PgConfig,
+    db_name: &str,
+    expected: &CreateDbOptions,
+    tls: T,
+) where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let session = AdminSession::connect(config, tls)
+        .await
+        .expect("failed to connect to the admin database");
+    let client = session.client();
+    let statement = include_str!("../sql/fetch_database_settings.sql").trim();
+    let row = client
+        .query_opt(
+            statement,
+            &[&expected.identifier_mode.catalog_name(db_name)],
+        )
+        .await
+        .expect("failed to read the database settings")
+        .unwrap_or_else(|| panic!("the `{}` database doesn't exist", db_name));
+
+    let mut diff = vec![];
+    let mut compare = |setting: &str, expected: Option<String>, found: String| {
+        if let Some(expected) = expected {
+            if !expected.eq_ignore_ascii_case(&found) {
+                diff.push(format!(
+                    "  {}: expected `{}`, found `{}`",
+                    setting, expected, found
+                ));
+            }
+        }
+    };
+    compare("encoding", expected.encoding.clone(), row.get(0));
+    compare("lc_collate", expected.lc_collate.clone(), row.get(1));
+    compare("lc_ctype", expected.lc_ctype.clone(), row.get(2));
+    let owner = match &expected.owner {
+        Some(Owner::CurrentUser) => Some(current_user(client, "current_user").await),
+        Some(Owner::SessionUser) => Some(current_user(client, "session_user").await),
+        Some(Owner::Named(name)) => Some(name.clone()),
+        None => None,
+    };
+    compare("owner", owner, row.get(3));
+    compare(
+        "connection_limit",
+        expected.connection_limit.map(|limit| limit.to_string()),
+        row.get::<_, i32>(4).to_string(),
+    );
+    compare(
+        "is_template",
+        expected
+            .is_template
+            .map(|is_template| is_template.to_string()),
+        row.get::<_, bool>(5).to_string(),
+    );
+    compare(
+        "allow_connections",
+        expected.allow_connections.map(|allow| allow.to_string()),
+        row.get::<_, bool>(6).to_string(),
+    );
+
+    if !diff.is_empty() {
+        panic!(
+            "the `{}` database doesn't match the expected options:\n{}",
+            db_name,
+            diff.join("\n")
+        );
+    }
+}
+
+async fn current_user(client: &Client, keyword: &str) -> String {
+    let statement = format!("SELECT {}::text", keyword);
+    client
+        .query_one(statement.as_str(), &[])
+        .await
+        .expect("failed to read the current user")
+        .get(0)
+}
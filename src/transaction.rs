@@ -0,0 +1,48 @@
+use deadpool_postgres::tokio_postgres::{Client, IsolationLevel, Transaction};
+
+use crate::CustomError;
+
+/// Starts a `REPEATABLE READ READ ONLY` transaction, so that every query run through it sees the same snapshot of the database.
+///
+/// This is meant for consistent reports made of several queries: rows committed by other sessions after the first query of the transaction are not visible to the next ones.
+/// Any attempt to write through the returned [`Transaction`] fails with *"25006", "cannot execute in a read-only transaction"*.
+///
+/// The transaction is rolled back when the returned [`Transaction`] is dropped, call [`Transaction::commit`] to end it explicitly.
+///
+/// # Errors
+///
+/// See [`CustomError`] for details.\
+/// Although it is rare for a read-only transaction, a serialization failure is reported with the *"40001"* code, in which case the whole report should be retried.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::{config::Config,NoTls};
+/// use pglit::{begin_readonly_snapshot, connect};
+///
+/// async fn report() {
+///     let mut config = Config::new();
+///     config.user("testuser");
+///     config.password("secretPassword");
+///     let (mut client, connection) = connect(config, "testdb", NoTls).await.unwrap();
+///     tokio::spawn(async move {
+///         if let Err(e) = connection.await {
+///             eprintln!("connection error: {}", e);
+///         }
+///     });
+///     let snapshot = begin_readonly_snapshot(&mut client).await.unwrap();
+///     let students = snapshot.query("SELECT * FROM student", &[]).await;
+///     let total = snapshot.query_one("SELECT count(*) FROM student", &[]).await;
+///     snapshot.commit().await.unwrap();
+/// }
+/// ```
+///
+pub async fn begin_readonly_snapshot(client: &mut Client) -> Result<Transaction<'_>, CustomError> {
+    client
+        .build_transaction()
+        .isolation_level(IsolationLevel::RepeatableRead)
+        .read_only(true)
+        .start()
+        .await
+        .map_err(CustomError::new)
+}
@@ -1,17 +1,40 @@
+use std::{fmt, future::Future, time::Duration};
+
 use deadpool_postgres::tokio_postgres::{
-    tls::MakeTlsConnect, tls::TlsConnect, Config as PgConfig, Socket,
+    config::Host, error::SqlState, tls::MakeTlsConnect, tls::TlsConnect, Config as PgConfig,
+    Error as TokioError, Socket,
 };
 
+use tokio::sync::mpsc::Sender;
+
+use crate::admin::AdminSession;
+use crate::events::{emit, PglitEvent};
+use crate::options::{AdminTarget, CreateDbOptions, IdentifierMode};
+
+use errors::ErrorKind;
+
 type CustomError = errors::CustomError;
-const ADMIN_DB: &str = "postgres";
+pub(crate) const ADMIN_DB: &str = "postgres";
+/// The admin databases tried in order, `template1` being used when the server has no `postgres` database.
+pub(crate) const ADMIN_DBS: &[&str] = &[ADMIN_DB, "template1"];
 
 /// Handles creating and dropping the database
+///
+/// The names are written in the statement according to the `mode`.
+/// The admin connection uses the `admin_target` host and port if any.
+/// The connection and the statement are each bounded by the `timeout` if any, a statement that times out is cancelled on the server.
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn handle_db<F, T, U>(
     config: &mut PgConfig,
     db_name: &str,
     tls: T,
     mut cb: F,
     action: &str,
+    options: Option<&CreateDbOptions>,
+    mode: IdentifierMode,
+    admin_target: Option<&AdminTarget>,
+    timeout: Option<Duration>,
+    events: Option<&Sender<PglitEvent>>,
 ) -> U
 where
     F: FnMut(Result<u64, CustomError>) -> U,
@@ -23,42 +46,370 @@ where
     if db_name.is_empty() {
         panic!("The database name in the `db_name` argument should not be empty");
     }
+    emit(events, || PglitEvent::Started {
+        action: match action {
+            "CREATE" => "CREATE",
+            "DROP" => "DROP",
+            "DROP IF EXISTS" => "DROP IF EXISTS",
+            _ => "FORCEDROP",
+        },
+        db_name: db_name.to_string(),
+    })
+    .await;
     let _ = config.dbname(ADMIN_DB);
-    let mut db_name = db_name.to_string();
 
-    if cfg!(feature = "quotes") {
-        let escaped_db_name = db_name.replace('\"', "");
-        db_name = format!(r#""{}""#, escaped_db_name);
-    }
-    // Todo handle id db name is empty
-    let db_name = db_name.as_str();
-    match config.connect(tls).await {
-        Ok((client, connection)) => {
-            let db_sql = get_sql_statement(action, db_name);
-            let _ = config.dbname(db_name);
-            // Note : to be changed
-            let _ = tokio::spawn(async move {
-                if let Err(e) = connection.await {
-                    eprintln!("connection error: {}", e);
-                }
-            });
+    let admin_config = match admin_target {
+        Some(target) => target.config(config),
+        None => config.clone(),
+    };
+    let session = with_timeout(
+        timeout,
+        "connecting to the admin database",
+        AdminSession::connect(&admin_config, tls.clone()),
+    )
+    .await;
+    let result = match session {
+        Ok(session) => {
+            emit(events, || PglitEvent::AdminConnected {
+                admin_db: session.admin_db().to_string(),
+            })
+            .await;
+            let _ = config.dbname(mode.quote(db_name).as_str());
             // maybe handle error before passing the to call back
-            match (&client).execute(&db_sql, &[]).await {
-                Ok(res) => cb(Ok(res)),
-                Err(pgerror) => {
-                    // Note: review code check if error handeling is neccesary here
-                    cb(Err(errors::CustomError::new(pgerror)))
-                }
+            let result = with_timeout(
+                timeout,
+                "running the statement",
+                session.execute_db_action(db_name, action, options, mode, events),
+            )
+            .await;
+            if matches!(&result, Err(e) if e.kind() == ErrorKind::Timeout) {
+                // the server keeps running a statement whose future was dropped
+                let _ = session.client().cancel_token().cancel_query(tls).await;
             }
+            session.close().await;
+            result
         }
-        Err(pgerror) => {
+        Err(e) => {
             println!("cb received pg result");
-            cb(Err(errors::CustomError::new(pgerror)))
+            Err(e)
         }
+    };
+    emit(events, || PglitEvent::finished(&result)).await;
+    cb(result)
+}
+
+/// Awaits the `future`, failing with a `timeout` error naming the `stage` once the `duration` elapses, if any.
+pub(crate) async fn with_timeout<R, F>(
+    duration: Option<Duration>,
+    stage: &str,
+    future: F,
+) -> Result<R, CustomError>
+where
+    F: Future<Output = Result<R, CustomError>>,
+{
+    match duration {
+        Some(duration) => tokio::time::timeout(duration, future)
+            .await
+            .unwrap_or_else(|_elapsed| Err(CustomError::timeout(stage, duration))),
+        None => future.await,
     }
 }
 
-fn get_sql_statement(action: &str, db_name: &str) -> String {
+/// Encloses `name` in double quotes when the `quotes` feature is enabled, see [`IdentifierMode::default`].
+pub(crate) fn quote_identifier(name: &str) -> String {
+    IdentifierMode::default().quote(name)
+}
+
+/// Returns the name stored in the catalogs for `name`, unquoted names being folded to lower case by PostgreSQL.
+pub(crate) fn catalog_name(name: &str) -> String {
+    IdentifierMode::default().catalog_name(name)
+}
+
+/// Maximum length of an identifier in bytes, longer identifiers are truncated by PostgreSQL.
+const MAX_IDENTIFIER_LENGTH: usize = 63;
+
+/// Keywords that can't be used as unquoted names, such as a schema named `full`.
+const RESERVED_KEYWORDS: &[&str] = &[
+    "all",
+    "analyse",
+    "analyze",
+    "and",
+    "any",
+    "array",
+    "as",
+    "asc",
+    "asymmetric",
+    "authorization",
+    "binary",
+    "both",
+    "case",
+    "cast",
+    "check",
+    "collate",
+    "collation",
+    "column",
+    "concurrently",
+    "constraint",
+    "create",
+    "cross",
+    "current_catalog",
+    "current_date",
+    "current_role",
+    "current_schema",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "default",
+    "deferrable",
+    "desc",
+    "distinct",
+    "do",
+    "else",
+    "end",
+    "except",
+    "false",
+    "fetch",
+    "for",
+    "foreign",
+    "freeze",
+    "from",
+    "full",
+    "grant",
+    "group",
+    "having",
+    "ilike",
+    "in",
+    "initially",
+    "inner",
+    "intersect",
+    "into",
+    "is",
+    "isnull",
+    "join",
+    "lateral",
+    "leading",
+    "left",
+    "like",
+    "limit",
+    "localtime",
+    "localtimestamp",
+    "natural",
+    "not",
+    "notnull",
+    "null",
+    "offset",
+    "on",
+    "only",
+    "or",
+    "order",
+    "outer",
+    "overlaps",
+    "placing",
+    "primary",
+    "references",
+    "returning",
+    "right",
+    "select",
+    "session_user",
+    "similar",
+    "some",
+    "symmetric",
+    "system_user",
+    "table",
+    "tablesample",
+    "then",
+    "to",
+    "trailing",
+    "true",
+    "union",
+    "unique",
+    "user",
+    "using",
+    "variadic",
+    "verbose",
+    "when",
+    "where",
+    "window",
+    "with",
+];
+
+/// Checks if `name` can be used as a database, schema, table or role name by pglit.
+///
+/// A valid name is not empty and is at most 63 bytes long, the length above which PostgreSQL truncates identifiers.\
+/// By default names are used unquoted, so they also have to start with a letter or an underscore, contain only letters, digits,
+/// underscores and dollar signs, and not be a reserved keyword such as `user` or `full`.
+/// When the **`quotes`** feature is enabled names are enclosed in **double-quotes** ("), so any character other than the null character is accepted.
+///
+/// This makes it possible to reject a name supplied by a user up front, rather than getting a panic or a syntax error from PostgreSQL.
+///
+/// # Example
+///
+/// ```
+/// use pglit::is_valid_identifier;
+///
+/// assert!(is_valid_identifier("tenant_42"));
+/// assert!(!is_valid_identifier(""));
+/// assert!(!is_valid_identifier(&"a".repeat(64)));
+/// ```
+pub fn is_valid_identifier(name: &str) -> bool {
+    if name.is_empty() || name.len() > MAX_IDENTIFIER_LENGTH || name.contains('\0') {
+        return false;
+    }
+    if cfg!(feature = "quotes") {
+        return true;
+    }
+    let mut chars = name.chars();
+    let starts_with_letter = chars
+        .next()
+        .map_or(false, |c| c.is_alphabetic() || c == '_');
+    starts_with_letter
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        && !RESERVED_KEYWORDS.contains(&name.to_lowercase().as_str())
+}
+
+/// Returns `name` as it is written in the statements built by pglit, or `None` if it isn't a valid identifier.
+///
+/// The name is enclosed in **double-quotes** (") when the **`quotes`** feature is enabled, see [`is_valid_identifier`] for the validation rules.
+///
+/// # Example
+///
+/// ```
+/// use pglit::quote_identifier_checked;
+///
+/// assert!(quote_identifier_checked("tenant_42").is_some());
+/// assert_eq!(quote_identifier_checked(""), None);
+/// ```
+pub fn quote_identifier_checked(name: &str) -> Option<String> {
+    if is_valid_identifier(name) {
+        Some(quote_identifier(name))
+    } else {
+        None
+    }
+}
+
+/// Always encloses `name` in double quotes, doubling any embedded double quote.
+///
+/// This is used for names read from the catalogs, which have to be matched exactly.
+pub(crate) fn escape_identifier(name: &str) -> String {
+    format!(r#""{}""#, name.replace('"', r#""""#))
+}
+
+/// Encloses `value` in single quotes as a PostgreSQL string literal, doubling any embedded single quote.
+pub(crate) fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Defaults an empty schema name to the `public` schema.
+pub(crate) fn schema_or_public(schema_name: &str) -> &str {
+    if schema_name.is_empty() {
+        "public"
+    } else {
+        schema_name
+    }
+}
+
+/// Splits a `schema.table` name on its first dot outside of double quotes.
+///
+/// Quoted parts are unquoted and their doubled double quotes restored, unquoted parts are folded to lower case like PostgreSQL does.
+/// The schema is `None` when the name has no unquoted dot.
+pub(crate) fn split_qualified_name(qualified_name: &str) -> (Option<String>, String) {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut chars = qualified_name.trim().chars().peekable();
+    let mut in_quotes = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                part.push('"');
+                let _ = chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            '.' if !in_quotes && parts.is_empty() => parts.push(std::mem::take(&mut part)),
+            c if in_quotes => part.push(c),
+            c => part.extend(c.to_lowercase()),
+        }
+    }
+    parts.push(part);
+    let table = parts.pop().unwrap_or_default();
+    (parts.pop(), table)
+}
+
+/// Checks if an error is a connection-level failure that is worth retrying,
+/// such as an I/O error, a closed connection or a server that is still starting up.
+pub(crate) fn is_connection_error(error: &TokioError) -> bool {
+    let is_io_error = std::error::Error::source(error).map_or(false, |e| e.is::<std::io::Error>());
+    is_io_error || error.is_closed() || error.code() == Some(&SqlState::CANNOT_CONNECT_NOW)
+}
+
+/// Renders the connection target of a [`tokio_postgres::Config`][`PgConfig`] as a `key=value` string for logging, with the password masked.
+///
+/// The hosts, ports, user and database name are listed in that order, the unset ones are left out.
+/// The password is rendered as `****` when it is set, so the result is safe to log unlike the [`Debug`][`fmt::Debug`] output of the config.
+///
+/// # Example
+///
+/// ```
+/// use tokio_postgres::Config;
+/// use pglit::redacted_conninfo;
+///
+/// let mut config = Config::new();
+/// config.host("localhost").port(5432).user("app").password("secret").dbname("appdb");
+/// assert_eq!(
+///     redacted_conninfo(&config),
+///     "host=localhost port=5432 user=app password=**** dbname=appdb"
+/// );
+/// ```
+pub fn redacted_conninfo(config: &PgConfig) -> String {
+    let mut parts = vec![];
+    let hosts: Vec<String> = config
+        .get_hosts()
+        .iter()
+        .map(|host| match host {
+            Host::Tcp(host) => host.clone(),
+            #[cfg(unix)]
+            Host::Unix(path) => path.display().to_string(),
+        })
+        .collect();
+    if !hosts.is_empty() {
+        parts.push(format!("host={}", hosts.join(",")));
+    }
+    let ports: Vec<String> = config.get_ports().iter().map(u16::to_string).collect();
+    if !ports.is_empty() {
+        parts.push(format!("port={}", ports.join(",")));
+    }
+    if let Some(user) = config.get_user() {
+        parts.push(format!("user={}", user));
+    }
+    if config.get_password().is_some() {
+        parts.push("password=****".to_string());
+    }
+    if let Some(dbname) = config.get_dbname() {
+        parts.push(format!("dbname={}", dbname));
+    }
+    parts.join(" ")
+}
+
+/// Formats a [`PgConfig`] for diagnostics with the password redacted to `"****"`.
+pub(crate) struct RedactedConfig<'a>(pub(crate) &'a PgConfig);
+
+impl fmt::Debug for RedactedConfig<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let config = self.0;
+        f.debug_struct("Config")
+            .field("user", &config.get_user())
+            .field("password", &config.get_password().map(|_| "****"))
+            .field("dbname", &config.get_dbname())
+            .field("application_name", &config.get_application_name())
+            .field("host", &config.get_hosts())
+            .field("port", &config.get_ports())
+            .finish_non_exhaustive()
+    }
+}
+
+pub(crate) fn get_sql_statement(action: &str, db_name: &str) -> String {
+    if action == "DROP IF EXISTS" {
+        return get_sql_statement("DROP", &format!("IF EXISTS {}", db_name));
+    }
     let stm = action.split(',').collect::<Vec<&str>>();
     let db_sql = include_str!("../sql/create_or_drop_db.sql").replace("$db_name", db_name);
     let mut db_sql = db_sql.replace("$action", stm[0]).trim().to_string();
@@ -71,7 +422,10 @@ fn get_sql_statement(action: &str, db_name: &str) -> String {
 
 /// A convenient way to access the error message and code
 pub(crate) mod errors {
-    use deadpool_postgres::tokio_postgres::Error as PGError;
+    use std::time::Duration;
+
+    use deadpool::managed::BuildError;
+    use deadpool_postgres::{tokio_postgres::Error as PGError, ConfigError, CreatePoolError};
 
     /// Wrapper to make it convenient to access the error message and code or the entire [`tokio_postgres::Error`][`PGError`].
     #[derive(Debug)]
@@ -81,25 +435,204 @@ pub(crate) mod errors {
         ///Error Code
         pub code: String,
         ///Postgres Error
-        pub pg_error: PGError,
+        ///
+        /// It is `None` when the error doesn't come from postgres, such as a timeout whose `code` is `"timeout"`
+        /// or a connection that couldn't be checked out of a pool whose `code` is `"pool"`.
+        pub pg_error: Option<PGError>,
+        /// The statement generated by pglit that failed, such as the `CREATE DATABASE` statement of [`create_db`][`crate::create_db`].
+        ///
+        /// It is only set for the create and drop database statements, which contain identifiers and database settings but never a password.
+        pub statement: Option<String>,
+        /// The sessions still connected to the database when dropping it failed with *"55006", "database is being accessed by other users"*.
+        ///
+        /// It is empty for any other error.
+        pub connections: Vec<ActiveConnection>,
     }
     impl CustomError {
         #[must_use]
         /// Create a new [`CustomError`]
         pub(crate) fn new(error: PGError) -> CustomError {
             CustomError {
-                message: if error.as_db_error() == None {
+                message: if error.as_db_error().is_none() {
                     "".to_string()
                 } else {
                     error.as_db_error().unwrap().message().replace('\"', "")
                 },
-                code: if error.code() == None {
+                code: if error.code().is_none() {
                     "".to_string()
                 } else {
                     error.code().unwrap().code().to_string()
                 },
-                pg_error: error,
+                pg_error: Some(error),
+                statement: None,
+                connections: vec![],
+            }
+        }
+    }
+
+    impl CustomError {
+        /// Create the [`CustomError`] of an operation that didn't complete within `duration`, it has no `pg_error`.
+        pub(crate) fn timeout(stage: &str, duration: Duration) -> CustomError {
+            CustomError {
+                message: format!("timed out after {:?} {}", duration, stage),
+                code: "timeout".to_string(),
+                pg_error: None,
+                statement: None,
+                connections: vec![],
+            }
+        }
+
+        /// Create the [`CustomError`] of a connection that couldn't be checked out of a pool for another reason than a postgres error,
+        /// such as a closed pool, it has no `pg_error`.
+        pub(crate) fn pool(error: impl std::fmt::Display) -> CustomError {
+            CustomError {
+                message: error.to_string(),
+                code: "pool".to_string(),
+                pg_error: None,
+                statement: None,
+                connections: vec![],
+            }
+        }
+
+        /// Returns the wrapped postgres error, or the error itself when it has none such as a timeout or a pool error.
+        pub(crate) fn into_pg_error(mut self) -> Result<PGError, CustomError> {
+            match self.pg_error.take() {
+                Some(error) => Ok(error),
+                None => Err(self),
+            }
+        }
+
+        /// Converts the error for the functions returning a [`CreatePoolError`], the postgres error becoming a [`BuildError::Backend`].
+        ///
+        /// [`BuildError`] has no other variant carrying a message, so an error without a postgres error, such as a timeout or a pool error,
+        /// is returned as a [`BuildError::NoRuntimeSpecified`] with its message.
+        pub(crate) fn into_create_pool_error(self) -> CreatePoolError {
+            match self.into_pg_error() {
+                Ok(error) => CreatePoolError::Build(BuildError::Backend(error)),
+                Err(error) => CreatePoolError::Build(BuildError::NoRuntimeSpecified(error.message)),
+            }
+        }
+
+        /// Returns the broad category of the error, such as an unreachable server or a missing privilege.
+        pub fn kind(&self) -> ErrorKind {
+            if self.code == "42501" {
+                ErrorKind::InsufficientPrivilege
+            } else if self.code == "timeout" {
+                ErrorKind::Timeout
+            } else if self
+                .pg_error
+                .as_ref()
+                .map_or(false, super::is_connection_error)
+            {
+                ErrorKind::Connection
+            } else {
+                ErrorKind::Other
             }
         }
     }
+
+    /// A session connected to a database, as listed in [`CustomError::connections`].
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ActiveConnection {
+        /// The process id of the backend, which can be passed to [`terminate_backend`][`crate::terminate_backend`].
+        pub pid: i32,
+        /// The user of the session, `None` for background processes.
+        pub user: Option<String>,
+        /// The `application_name` of the session.
+        pub application_name: Option<String>,
+        /// The address of the client, `None` for a Unix socket connection.
+        pub client_addr: Option<String>,
+    }
+
+    /// Broad category of a [`CustomError`], see [`CustomError::kind`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ErrorKind {
+        /// The server couldn't be reached or the connection was lost.
+        Connection,
+        /// The connection succeeded but the user isn't allowed to run the statement (`42501`),
+        /// such as a user without the `CREATEDB` privilege creating a database.
+        InsufficientPrivilege,
+        /// The operation didn't complete within the timeout it was given, the `code` of the [`CustomError`] is `"timeout"`.
+        Timeout,
+        /// Any other error, see the `code` of the [`CustomError`] for details.
+        Other,
+    }
+
+    impl From<PGError> for CustomError {
+        /// Wraps a [`tokio_postgres::Error`][`PGError`] obtained outside of pglit, so it can be handled like the errors returned by pglit.
+        fn from(error: PGError) -> CustomError {
+            CustomError::new(error)
+        }
+    }
+
+    /// Error returned by [`reconcile_db`][`crate::reconcile_db`].
+    #[derive(Debug)]
+    pub enum ReconcileError {
+        /// A statement failed, see [`CustomError`] for details.
+        Postgres(CustomError),
+        /// A setting that can't be altered differs between the existing database and the requested options.
+        Mismatch {
+            /// Name of the setting, such as `encoding`.
+            setting: &'static str,
+            /// Value of the setting in the existing database.
+            existing: String,
+            /// Value of the setting in the requested options.
+            requested: String,
+        },
+    }
+
+    impl From<CustomError> for ReconcileError {
+        fn from(error: CustomError) -> ReconcileError {
+            ReconcileError::Postgres(error)
+        }
+    }
+
+    /// Error returned by [`vacuum_table`][`crate::vacuum_table`].
+    #[derive(Debug)]
+    pub enum VacuumError {
+        /// A statement failed, see [`CustomError`] for details.
+        Postgres(CustomError),
+        /// `VACUUM FULL` was refused because the table exceeds the `full_size_limit` of the options.
+        TableTooLarge {
+            /// Total size of the table in bytes.
+            size: i64,
+            /// The `full_size_limit` of the options.
+            limit: i64,
+        },
+    }
+
+    impl From<CustomError> for VacuumError {
+        fn from(error: CustomError) -> VacuumError {
+            VacuumError::Postgres(error)
+        }
+    }
+
+    /// Error returned by [`apply_migrations`][`crate::apply_migrations`].
+    #[derive(Debug)]
+    pub enum MigrationError {
+        /// The `pglit_migrations` table couldn't be created, see [`CustomError`] for details.
+        Postgres(CustomError),
+        /// A migration failed and was rolled back, the migrations after it weren't run.
+        Failed {
+            /// The version of the failed migration.
+            version: String,
+            /// The error of the migration, see [`CustomError`] for details.
+            error: CustomError,
+        },
+    }
+
+    impl From<CustomError> for MigrationError {
+        fn from(error: CustomError) -> MigrationError {
+            MigrationError::Postgres(error)
+        }
+    }
+
+    /// Error returned by [`connect_from_deadpool_config`][`crate::connect_from_deadpool_config`].
+    #[derive(Debug)]
+    pub enum ConnectConfigError {
+        /// The [`deadpool_postgres::Config`](https://docs.rs/deadpool-postgres/0.10.1/deadpool_postgres/struct.Config.html) is invalid, such as a missing `dbname`.
+        Config(ConfigError),
+        /// Connecting to the database or creating it failed.
+        Postgres(PGError),
+    }
 }
@@ -1,10 +1,33 @@
 #![allow(dead_code, unused_macros, unused_imports)]
 
 use deadpool_postgres::tokio_postgres::{config::Config as tkconfig, NoTls};
-use deadpool_postgres::{Config as dpconfig, ConfigError, Pool};
+use deadpool_postgres::{Config as dpconfig, ConfigError, Pool, PoolConfig, PoolError, Runtime};
 use dotenv::dotenv;
 use pglit::{
-    connect, create_db, create_schemas, deadpool_create_db, drop_db, forcedrop_db, table_exists,
+    alter_schema_owner, analyze_table, apply_migrations, apply_spec, begin_readonly_snapshot,
+    can_create_databases, cascade_dependents, checkpoint, clone_db, column_exists, connect,
+    connect_from_deadpool_config, connect_managed, connect_pooled, connect_with_retry,
+    connect_with_session, connection_limit, create_db, create_db_if_missing,
+    create_db_if_not_exists, create_db_with_client, create_db_with_events, create_db_with_options,
+    create_db_with_owner, create_extension, create_schemas, create_schemas_in,
+    create_schemas_with_search_path, create_table, database_exists, database_object_summary,
+    deadpool_create_db, deadpool_create_db_with_retry, deadpool_create_db_with_session,
+    drop_databases_with_prefix, drop_db, drop_db_if_exists, drop_db_with_client,
+    drop_db_with_events, drop_db_with_options, drop_db_with_pool, drop_schema_if_empty,
+    drop_schemas, drop_table, ensure_app_role, ensure_database, extension_exists, forcedrop_db,
+    forcedrop_db_unguarded, forcedrop_db_with_client, foreign_keys, grant_all_on_database,
+    grant_privileges, is_valid_identifier, list_databases, list_extensions, list_indexes,
+    list_schemas, list_sequences, provision_db, qualified_table_exists, quote_identifier_checked,
+    reconcile_db, redacted_conninfo, reload_conf, rename_db, rename_table, reset_role,
+    reset_sequence, revoke_privileges, row_exists, run_admin_query, schema_exists, server_encoding,
+    set_connection_limit, set_role, slow_queries, table_exists, table_privileges,
+    table_row_estimate, tables_exist, terminate_backend, timezone, truncate_all_tables,
+    try_create_db, try_drop_db, try_forcedrop_db, try_table_exists, update_extension, vacuum_table,
+    verify_pool, with_admin_client, with_client, AdminSession, AdminTarget, ColumnDef, Confirm,
+    ConnectConfigError, CreateDbOptions, CustomError, DatabaseSpec, DbSummary, DropDbOptions,
+    ErrorKind, ExtensionInfo, ForeignKeyInfo, GrantObject, GrantSpec, IdentifierMode, IndexInfo,
+    MigrationError, Owner, PgLit, PglitEvent, QueryStat, ReconcileError, RetryPolicy, SchemaSpec,
+    SearchPathScope, SessionInit, SpecOutcome, VacuumError, VacuumOptions,
 };
 
 use serde::{Deserialize, Serialize};
@@ -40,15 +63,9 @@ fn get_deadpool_config() -> dpconfig {
 }
 
 async fn reset_test(config: &mut tkconfig, db_name: &str) {
-    drop_db(&mut config.clone(), db_name, NoTls, |res| match res {
+    drop_db_if_exists(&mut config.clone(), db_name, NoTls, |res| match res {
         Ok(_n) => eprintln!("db successfuly deleted"),
-        Err(e) => {
-            if e.code == "3D000" {
-                eprintln!("attempting to delete a db that doesn't exist");
-            } else {
-                eprintln!("{}", e.message)
-            }
-        }
+        Err(e) => eprintln!("{}", e.message),
     })
     .await;
 }
@@ -66,6 +83,14 @@ mod panic_tests {
         create_db(&mut config.clone(), db_name, NoTls, |_res| {}).await;
     }
 
+    #[tokio::test]
+    #[should_panic(expected = "The database name in the `db_name` argument should not be empty")]
+    async fn create_if_not_exists_dbname_empty() {
+        let config = get_tokio_config();
+        let db_name = "";
+        create_db_if_not_exists(&mut config.clone(), db_name, NoTls, |_res| {}).await;
+    }
+
     #[tokio::test]
     #[should_panic(expected = "The database name in the `db_name` argument should not be empty")]
     async fn drop_dbname_empty() {
@@ -79,7 +104,14 @@ mod panic_tests {
     async fn forcedrop_dbname_empty() {
         let config = get_tokio_config();
         let db_name = "";
-        forcedrop_db(&mut config.clone(), db_name, NoTls, |_res| {}).await;
+        forcedrop_db(
+            &mut config.clone(),
+            db_name,
+            NoTls,
+            Confirm::force(),
+            |_res| {},
+        )
+        .await;
     }
     #[tokio::test]
     #[should_panic(expected = "the `table_name` argument should not be empty")]
@@ -107,7 +139,19 @@ mod panic_tests {
                 eprintln!("connection error: {}", e);
             }
         });
-        create_schemas(&client, vec![], false, |_res| {}).await;
+        create_schemas(&client, &[], false, |_res| {}).await;
+    }
+    #[tokio::test]
+    #[should_panic(expected = "the `schema_name` argument should not be empty")]
+    async fn drop_schema_if_empty_panic() {
+        let config = get_tokio_config();
+        let (client, connection) = connect(config.clone(), "testdb", NoTls).await.unwrap();
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("connection error: {}", e);
+            }
+        });
+        drop_schema_if_empty(&client, "").await.unwrap();
     }
 }
 
@@ -235,7 +279,7 @@ async fn connect_db_test() {
 
     match try_connect_handle_duplicate {
         Ok((client, connection)) => {
-            let _ = tokio::spawn(async move {
+            tokio::spawn(async move {
                 if let Err(e) = connection.await {
                     eprintln!("connection error: {}", e);
                 }
@@ -275,6 +319,37 @@ async fn connect_db_test() {
     }
 }
 
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn connect_with_retry_test() {
+    let mut config = get_tokio_config();
+    let db_name = "pglit_connect_retry";
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let retry = RetryPolicy {
+        max_retries: 2,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+    };
+    let (client, connection) = connect_with_retry(config.clone(), db_name, NoTls, retry)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let row = client.query_one("SELECT current_database()", &[]).await;
+    assert_eq!(row.unwrap().get::<_, String>(0), db_name);
+
+    // authentication failures are not retried
+    let mut wrong_password = config.clone();
+    wrong_password.password("wrong-password");
+    let res = connect_with_retry(wrong_password, db_name, NoTls, retry).await;
+    assert!(res.is_err());
+}
+
 #[cfg(not(feature = "quotes"))]
 #[tokio::test]
 async fn create_db_and_get_pool() {
@@ -342,71 +417,235 @@ async fn create_db_and_get_pool() {
     assert!(deadpool_create_db(cfg, None, NoTls).await.is_ok());
 }
 
-use std::sync::Arc;
 #[cfg(not(feature = "quotes"))]
 #[tokio::test]
-async fn forcedrop_test() {
+async fn drop_db_with_pool_test() {
+    let mut cfg = get_deadpool_config();
+    let config = get_tokio_config();
+    let db_name = "pglit_drop_with_pool";
+    cfg.dbname = Some(String::from(db_name));
+
+    let pool = deadpool_create_db(cfg, None, NoTls).await.unwrap();
+    {
+        // leave an idle connection in the pool
+        let db_conn = pool.get().await.unwrap();
+        let _ = db_conn.query("SELECT 1", &[]).await.unwrap();
+    }
+    assert_eq!(pool.status().size, 1);
+
+    drop_db_with_pool(&pool, &mut config.clone(), db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    assert!(pool.is_closed());
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn pglit_into_pool_test() {
     let mut config = get_tokio_config();
-    let (client, connection) = connect(config.clone(), "db_test", NoTls).await.unwrap();
+    let db_name = "pglit_into_pool";
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+    config.dbname(db_name);
+
+    let (pool, created) = PgLit::new(config.clone(), NoTls)
+        .into_pool(None)
+        .await
+        .unwrap();
+    assert!(created);
+    let db_conn = pool.get().await.unwrap();
+    let row = db_conn.query_one("SELECT current_database()", &[]).await;
+    assert_eq!(row.unwrap().get::<_, String>(0), db_name);
+
+    // the database already exists
+    let (_pool, created) = PgLit::new(config.clone(), NoTls)
+        .into_pool(None)
+        .await
+        .unwrap();
+    assert!(!created);
+
+    // a missing database name is a config error
+    config.dbname("");
+    let result = PgLit::new(config, NoTls).into_pool(None).await;
+    assert_err!(
+        result,
+        Err(deadpool::managed::CreatePoolError::Config(
+            ConfigError::DbnameMissing
+        ))
+    );
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn ensure_database_test() {
+    let mut config = get_tokio_config();
+    let db_name = "pglit_ensure_database";
+    reset_test(&mut config, db_name).await;
+    config.dbname(db_name);
+
+    ensure_database(&config, NoTls).await.unwrap();
+    // the database already exists
+    ensure_database(&config, NoTls).await.unwrap();
+
+    let (client, connection) = config.connect(NoTls).await.unwrap();
     tokio::spawn(async move {
         if let Err(e) = connection.await {
             eprintln!("connection error: {}", e);
         }
     });
-    {
-        let table = include_str!("./sql/create_table_test.sql");
-        let _ = client.query(table, &[]).await;
-    }
-    let client_ref = Arc::new(client);
+    let row = client.query_one("SELECT current_database()", &[]).await;
+    assert_eq!(row.unwrap().get::<_, String>(0), db_name);
+}
 
-    let mut tasks = vec![];
-    for _i in 0..2 {
-        let client = client_ref.clone();
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn set_role_test() {
+    let db_name = "pglit_set_role";
+    let role_name = "pglit_set_role_user";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
 
-        tasks.push(tokio::spawn(async move {
-            let text = include_str!("./sql/insert_into_table_test.sql");
-            let _ = client
-                .query(
-                    text,
-                    &[
-                        &"joe",
-                        &"doe",
-                        &"9",
-                        &"88 Colin P Kelly Jr St, San Francisco, CA 94107, United States",
-                        &"joe.doe@example.com",
-                    ],
-                )
-                .await;
-        }));
-    }
-    tasks.push(tokio::spawn(async move {
-        //assert that db can't be dropped because is being accessed by other users
-        drop_db(&mut config, "db_test", NoTls, |result| {
-            assert!(result.is_err());
-            if let Err(e) = result {
-                assert_eq!(e.code, "55006");
-                eprintln!("Error dropping db {:?}", e);
-            }
-        })
-        .await;
-        //force drop db
-        forcedrop_db(&mut config, "db_test", NoTls, |result| {
-            assert!(result.is_ok());
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    ensure_app_role(&config, db_name, role_name, "secret", NoTls)
+        .await
+        .unwrap();
+    let user_name = |row: tokio_postgres::Row| row.get::<_, String>(0);
+    let session_user = user_name(
+        client
+            .query_one("SELECT current_user::text", &[])
+            .await
+            .unwrap(),
+    );
+
+    set_role(&client, role_name).await.unwrap();
+    let row = client
+        .query_one("SELECT current_user::text", &[])
+        .await
+        .unwrap();
+    assert_eq!(user_name(row), role_name);
+
+    reset_role(&client).await.unwrap();
+    let row = client
+        .query_one("SELECT current_user::text", &[])
+        .await
+        .unwrap();
+    assert_eq!(user_name(row), session_user);
+
+    let res = set_role(&client, "pglit_missing_role").await;
+    assert_eq!(res.unwrap_err().code, "22023");
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn connection_limit_test() {
+    let db_name = "pglit_connection_limit";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+    create_db(&mut config.clone(), db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    assert_eq!(connection_limit(&config, db_name, NoTls).await.unwrap(), -1);
+
+    set_connection_limit(&config, db_name, 7, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    assert_eq!(connection_limit(&config, db_name, NoTls).await.unwrap(), 7);
+    // unquoted names are folded to lower case, by both functions
+    set_connection_limit(&config, "PGLIT_Connection_Limit", 8, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    assert_eq!(
+        connection_limit(&config, "PGLIT_Connection_Limit", NoTls)
+            .await
+            .unwrap(),
+        8
+    );
+
+    set_connection_limit(&config, db_name, -1, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    assert_eq!(connection_limit(&config, db_name, NoTls).await.unwrap(), -1);
+
+    assert!(connection_limit(&config, "pglit_missing_db", NoTls)
+        .await
+        .is_err());
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn can_create_databases_test() {
+    let db_name = "pglit_can_create_databases";
+    let role_name = "pglit_no_createdb";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+    create_db(&mut config.clone(), db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+
+    // the test user is expected to be allowed to create databases
+    assert!(can_create_databases(&config, NoTls).await.unwrap());
+
+    ensure_app_role(&config, db_name, role_name, "secret", NoTls)
+        .await
+        .unwrap();
+    let mut role_config = config.clone();
+    role_config.user(role_name).password("secret");
+    assert!(!can_create_databases(&role_config, NoTls).await.unwrap());
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn with_client_test() {
+    let db_name = "pglit_with_client";
+    let app_name = "pglit_with_client_test";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+
+    let mut app_config = config.clone();
+    app_config.application_name(app_name);
+    let name = with_client(app_config, db_name, NoTls, |client| {
+        Box::pin(async move {
+            let row = client.query_one("SELECT current_database()", &[]).await;
+            row.unwrap().get::<_, String>(0)
         })
+    })
+    .await
+    .unwrap();
+    assert_eq!(name, db_name);
+
+    // the connection has been closed when `with_client` returns
+    let statement = "SELECT pid FROM pg_stat_activity WHERE application_name = $1";
+    let mut rows = run_admin_query(&config, statement, &[&app_name], NoTls)
         .await
-    }));
-    for handle in tasks {
-        let _ = handle.await;
+        .unwrap();
+    for _ in 0..10 {
+        if rows.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        rows = run_admin_query(&config, statement, &[&app_name], NoTls)
+            .await
+            .unwrap();
     }
+    assert!(rows.is_empty());
 }
 
 #[cfg(not(feature = "quotes"))]
 #[tokio::test]
-async fn table_exists_test() {
-    let db_name = "pglitdb";
-    let mut config = get_tokio_config();
-    //reset test if run more than once
-    let _ = reset_test(&mut config, db_name).await;
+async fn terminate_backend_test() {
+    let db_name = "pglit_terminate_backend";
+    let config = get_tokio_config();
 
     let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
     tokio::spawn(async move {
@@ -414,40 +653,3496 @@ async fn table_exists_test() {
             eprintln!("connection error: {}", e);
         }
     });
-    //the `table_name` argument should not be empty
-    let tb_exist = table_exists(&client, "", "student").await;
-    assert!(!tb_exist);
+    let row = client.query_one("SELECT pg_backend_pid()", &[]).await;
+    let pid: i32 = row.unwrap().get(0);
 
-    let table = include_str!("./sql/create_table_test.sql");
-    let _ = client.query(table, &[]).await;
-    assert!(table_exists(&client, "", "student").await);
+    assert!(terminate_backend(&config, pid, NoTls).await.unwrap());
+    let mut terminated = false;
+    for _ in 0..10 {
+        if client.query_one("SELECT 1", &[]).await.is_err() {
+            terminated = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(terminated);
+
+    // not a postgres backend
+    assert!(!terminate_backend(&config, i32::MAX, NoTls).await.unwrap());
 }
 
-//#[cfg(not(feature = "quotes"))]
+#[cfg(not(feature = "quotes"))]
 #[tokio::test]
-async fn create_schema_test() {
-    let db_name = "pglit";
-    let config = get_tokio_config();
+async fn checkpoint_and_reload_conf_test() {
+    let db_name = "pglit_checkpoint";
+    let role_name = "pglit_checkpoint_user";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+    create_db(&mut config.clone(), db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+
+    // the test user is expected to be a superuser
+    checkpoint(&config, NoTls).await.unwrap();
+    reload_conf(&config, NoTls).await.unwrap();
+
+    ensure_app_role(&config, db_name, role_name, "secret", NoTls)
+        .await
+        .unwrap();
+    let mut role_config = config.clone();
+    role_config.user(role_name).password("secret");
+    assert_eq!(
+        checkpoint(&role_config, NoTls).await.unwrap_err().code,
+        "42501"
+    );
+    assert_eq!(
+        reload_conf(&role_config, NoTls).await.unwrap_err().code,
+        "42501"
+    );
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn provision_db_test() {
+    let db_name = "pglit_provision_db";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+    let statement =
+        "SELECT shobj_description(oid, 'pg_database') FROM pg_database WHERE datname = $1";
+    let options = CreateDbOptions::default();
+
+    let created = provision_db(&config, db_name, &options, Some("tenant's db"), NoTls).await;
+    assert!(created.unwrap());
+    let rows = run_admin_query(&config, statement, &[&db_name], NoTls)
+        .await
+        .unwrap();
+    assert_eq!(rows[0].get::<_, String>(0), "tenant's db");
+
+    // the database already exists, only the comment is updated
+    let created = provision_db(&config, db_name, &options, Some("renamed"), NoTls).await;
+    assert!(!created.unwrap());
+    let rows = run_admin_query(&config, statement, &[&db_name], NoTls)
+        .await
+        .unwrap();
+    assert_eq!(rows[0].get::<_, String>(0), "renamed");
+
+    // CREATE DATABASE fails inside a transaction block
+    reset_test(&mut config, db_name).await;
+    let session = AdminSession::connect(&config, NoTls).await.unwrap();
+    assert_eq!(session.admin_db(), "postgres");
+    session.client().batch_execute("BEGIN").await.unwrap();
+    create_db_with_client(session.client(), db_name, |res| {
+        let e = res.unwrap_err();
+        assert_eq!(e.code, "25001");
+        assert!(e.message.contains("outside of a transaction"));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn provision_db_quoted_test() {
+    let db_name = "Pglit_Provision_Quoted";
+    let mut config = get_tokio_config();
+    let drop_options = DropDbOptions {
+        identifier_mode: IdentifierMode::Quoted,
+        ..DropDbOptions::default()
+    };
     //reset test if run more than once
-    let _ = reset_test(&mut config.clone(), db_name).await;
+    drop_db_with_options(&mut config, db_name, NoTls, &drop_options, |_| ()).await;
 
-    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    let options = CreateDbOptions {
+        identifier_mode: IdentifierMode::Quoted,
+        ..CreateDbOptions::default()
+    };
+    // the comment targets the quoted name that was just created
+    let created = provision_db(&config, db_name, &options, Some("quoted"), NoTls).await;
+    assert!(created.unwrap());
+    let rows = run_admin_query(
+        &config,
+        "SELECT shobj_description(oid, 'pg_database') FROM pg_database WHERE datname = $1",
+        &[&db_name],
+        NoTls,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rows[0].get::<_, String>(0), "quoted");
+    drop_db_with_options(&mut config, db_name, NoTls, &drop_options, |res| {
+        res.unwrap()
+    })
+    .await;
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test]
+async fn assert_database_options_test() {
+    let db_name = "pglit_assert_database_options";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+    let options = CreateDbOptions {
+        owner: Some(Owner::CurrentUser),
+        encoding: Some("utf8".to_string()),
+        connection_limit: Some(3),
+        identifier_mode: IdentifierMode::Raw,
+        ..CreateDbOptions::default()
+    };
+    create_db_with_options(&mut config.clone(), db_name, NoTls, &options, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+
+    pglit::assert_database_options(&config, db_name, &options, NoTls).await;
+    // a raw name is looked up folded to lower case, the way it was created
+    pglit::assert_database_options(&config, "PGLIT_Assert_Database_Options", &options, NoTls).await;
+
+    let expected = CreateDbOptions {
+        connection_limit: Some(4),
+        is_template: Some(true),
+        ..CreateDbOptions::default()
+    };
+    let handle = tokio::spawn(async move {
+        pglit::assert_database_options(&config, db_name, &expected, NoTls).await;
+    });
+    let message = handle.await.unwrap_err().into_panic();
+    let message = message.downcast_ref::<String>().unwrap();
+    assert!(message.contains("connection_limit: expected `4`, found `3`"));
+    assert!(message.contains("is_template: expected `true`, found `false`"));
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn pglit_builder_test() {
+    let db_name = "pglit_builder";
+    let pglit = PgLit::builder()
+        .config(get_tokio_config())
+        .tls(NoTls)
+        .build();
+    pglit.drop_db(db_name, |_res| {}).await;
+
+    pglit
+        .create_db(db_name, |res| {
+            assert!(res.is_ok());
+        })
+        .await;
+    let (client, connection) = pglit.connect(db_name).await.unwrap();
     tokio::spawn(async move {
         if let Err(e) = connection.await {
             eprintln!("connection error: {}", e);
         }
     });
-    create_schemas(
-        &client,
-        vec!["sport", "", "sales", "books"],
-        true,
-        |_res| {},
-    )
-    .await;
-    let table = include_str!("./sql/create_table_test.sql");
-    let _ = client.query(table, &[]).await;
+    let row = client.query_one("SELECT current_database()", &[]).await;
+    assert_eq!(row.unwrap().get::<_, String>(0), db_name);
+    drop(client);
 
-    assert!(table_exists(&client, "sport", "student").await);
+    pglit
+        .forcedrop_db(db_name, Confirm::force(), |res| {
+            assert!(res.is_ok());
+        })
+        .await;
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn drop_databases_with_prefix_test() {
+    let prefix = "pglit_prefix_";
+    let config = get_tokio_config();
+    for db_name in ["pglit_prefix_a", "pglit_prefix_b", "pglit_prefixed"] {
+        create_db(&mut config.clone(), db_name, NoTls, |_res| {}).await;
+    }
+    let (client, connection) = connect(config.clone(), "pglit_prefix_a", NoTls)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+
+    // the open connection prevents dropping without the force option
+    let res = drop_databases_with_prefix(&config, prefix, false, NoTls).await;
+    assert_eq!(res.unwrap_err().code, "55006");
+
+    let dropped = drop_databases_with_prefix(&config, prefix, true, NoTls)
+        .await
+        .unwrap();
+    assert_eq!(dropped, vec!["pglit_prefix_a", "pglit_prefix_b"]);
+    assert!(client.query_one("SELECT 1", &[]).await.is_err());
+
+    let dropped = drop_databases_with_prefix(&config, prefix, true, NoTls)
+        .await
+        .unwrap();
+    assert!(dropped.is_empty());
+    forcedrop_db_unguarded(&mut config.clone(), "pglit_prefixed", NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn deadpool_create_db_with_retry_test() {
+    let db_name = "pglit_pool_retry";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+
+    let mut cfg = get_deadpool_config();
+    let server = format!(
+        "{}:{}",
+        cfg.host.clone().unwrap_or_else(|| "127.0.0.1".to_string()),
+        cfg.port.unwrap_or(5432)
+    );
+    // reserve a free port, the proxy to the server only starts listening on it after a delay
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+            .await
+            .unwrap();
+        loop {
+            let (mut inbound, _) = listener.accept().await.unwrap();
+            let server = server.clone();
+            tokio::spawn(async move {
+                let mut outbound = tokio::net::TcpStream::connect(server).await.unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+            });
+        }
+    });
+
+    cfg.host = Some("127.0.0.1".to_string());
+    cfg.port = Some(port);
+    cfg.dbname = Some(db_name.to_string());
+    let retry = RetryPolicy {
+        max_retries: 10,
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_millis(200),
+    };
+    let pool = deadpool_create_db_with_retry(cfg.clone(), None, NoTls, retry)
+        .await
+        .unwrap();
+    let db_conn = pool.get().await.unwrap();
+    let row = db_conn.query_one("SELECT current_database()", &[]).await;
+    assert_eq!(row.unwrap().get::<_, String>(0), db_name);
+
+    // a first checkout failing for another reason than postgres isn't reported as a usable pool
+    let mut pool_config = PoolConfig::new(1);
+    pool_config.timeouts.create = Some(Duration::from_nanos(1));
+    cfg.pool = Some(pool_config);
+    let result = deadpool_create_db_with_retry(cfg, Some(Runtime::Tokio1), NoTls, retry).await;
+    assert!(matches!(
+        result,
+        Err(deadpool::managed::CreatePoolError::Build(
+            deadpool::managed::BuildError::NoRuntimeSpecified(_)
+        ))
+    ));
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn server_encoding_and_timezone_test() {
+    let db_name = "pglit_server_settings";
+    let config = get_tokio_config();
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+
+    let row = client
+        .query_one("SELECT pg_encoding_to_char(encoding)::text FROM pg_database WHERE datname = current_database()", &[])
+        .await
+        .unwrap();
+    assert_eq!(
+        server_encoding(&client).await.unwrap(),
+        row.get::<_, String>(0)
+    );
+
+    client
+        .batch_execute("SET timezone TO 'Europe/Paris'")
+        .await
+        .unwrap();
+    assert_eq!(timezone(&client).await.unwrap(), "Europe/Paris");
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn database_object_summary_test() {
+    let db_name = "pglit_object_summary";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    create_schemas(&client, &["sales"], false, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    client
+        .batch_execute("CREATE TABLE sales.orders(id INT); CREATE TABLE public.items(id INT);")
+        .await
+        .unwrap();
+
+    let summary = database_object_summary(&config, db_name, NoTls)
+        .await
+        .unwrap();
+    assert_eq!(
+        summary,
+        DbSummary {
+            schemas: 2,
+            tables: 2,
+            connections: 1,
+        }
+    );
+
+    let res = database_object_summary(&config, "pglit_missing_db", NoTls).await;
+    assert_eq!(res.unwrap_err().code, "3D000");
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn connect_pooled_test() {
+    let db_name = "pglit_connect_pooled";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+
+    let (object, created) = connect_pooled(config.clone(), db_name, NoTls)
+        .await
+        .unwrap();
+    assert!(created);
+    let row = object.query_one("SELECT current_database()", &[]).await;
+    assert_eq!(row.unwrap().get::<_, String>(0), db_name);
+    drop(object);
+
+    let (_object, created) = connect_pooled(config.clone(), db_name, NoTls)
+        .await
+        .unwrap();
+    assert!(!created);
+
+    // an unreachable server is reported as an error instead of a panic
+    let mut unreachable = tkconfig::new();
+    let _ = unreachable
+        .host("127.0.0.1")
+        .port(1)
+        .user(config.get_user().unwrap());
+    let error = connect_pooled(unreachable, db_name, NoTls)
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Connection);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn session_init_test() {
+    let db_name = "pglit_session_init";
+    let role_name = "pglit_session_user";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+    create_db(&mut config.clone(), db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    ensure_app_role(&config, db_name, role_name, "secret", NoTls)
+        .await
+        .unwrap();
+    let session = SessionInit {
+        search_path: Some(vec!["tenant".to_string(), "public".to_string()]),
+        role: Some(role_name.to_string()),
+        statement_timeout: Some(Duration::from_millis(1500)),
+    };
+    let settings = "SELECT current_setting('search_path'), current_user::text, current_setting('statement_timeout')";
+    let expected = ("tenant, public", role_name, "1500ms");
+
+    let (client, connection) = connect_with_session(config.clone(), db_name, NoTls, &session)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let row = client.query_one(settings, &[]).await.unwrap();
+    assert_eq!((row.get(0), row.get(1), row.get(2)), expected);
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    session.apply(&client).await.unwrap();
+    let row = client.query_one(settings, &[]).await.unwrap();
+    assert_eq!((row.get(0), row.get(1), row.get(2)), expected);
+
+    // the startup option resolves the role like the `SET ROLE` of `apply`
+    let session = SessionInit {
+        role: Some("PGLIT_Session_User".to_string()),
+        ..session
+    };
+    let (client, connection) = connect_with_session(config.clone(), db_name, NoTls, &session)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let row = client.query_one(settings, &[]).await.unwrap();
+    assert_eq!((row.get(0), row.get(1), row.get(2)), expected);
+}
+
+#[cfg(feature = "quotes")]
+#[tokio::test]
+async fn session_init_quoted_role_test() {
+    let db_name = "pglit_session_quoted_role";
+    // the embedded double quote is part of the role name
+    let role_name = "pglit_session\"user";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+    create_db(&mut config.clone(), db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    ensure_app_role(&config, db_name, role_name, "secret", NoTls)
+        .await
+        .unwrap();
+    let session = SessionInit {
+        role: Some(role_name.to_string()),
+        ..SessionInit::default()
+    };
+
+    let (client, connection) = connect_with_session(config.clone(), db_name, NoTls, &session)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let row = client
+        .query_one("SELECT current_user::text", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, String>(0), role_name);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn deadpool_create_db_with_session_test() {
+    let db_name = "pglit_pool_search_path";
+    let mut config = get_deadpool_config();
+    reset_test(&mut get_tokio_config(), db_name).await;
+    config.dbname = Some(db_name.to_string());
+    let session = SessionInit {
+        search_path: Some(vec!["tenant".to_string(), "public".to_string()]),
+        ..SessionInit::default()
+    };
+
+    let pool = deadpool_create_db_with_session(config, None, NoTls, &session)
+        .await
+        .unwrap();
+    let first = pool.get().await.unwrap();
+    let second = pool.get().await.unwrap();
+    for client in [&first, &second] {
+        let row = client.query_one("SHOW search_path", &[]).await.unwrap();
+        assert_eq!(row.get::<_, &str>(0), "tenant, public");
+    }
+    first.batch_execute("RESET ALL").await.unwrap();
+    let row = first.query_one("SHOW search_path", &[]).await.unwrap();
+    assert_eq!(row.get::<_, &str>(0), "tenant, public");
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn ddl_with_pooled_client_test() {
+    let mut cfg = get_deadpool_config();
+    let db_name = "pglit_pooled_admin";
+    cfg.dbname = Some(String::from("postgres"));
+    let pool = cfg.create_pool(None, NoTls).unwrap();
+    let admin = pool.get().await.unwrap();
+
+    drop_db_with_client(&admin, db_name, |_res| {}).await;
+    create_db_with_client(&admin, db_name, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    create_db_with_client(&admin, db_name, |res| {
+        if let Err(e) = res {
+            assert_eq!(e.code, "42P04");
+        } else {
+            panic!("expected a duplicate database");
+        }
+    })
+    .await;
+    drop_db_with_client(&admin, db_name, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    create_db_with_client(&admin, db_name, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    forcedrop_db_with_client(&admin, db_name, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+}
+
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn forcedrop_test() {
+    let mut config = get_tokio_config();
+    let (client, connection) = connect(config.clone(), "db_test", NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    {
+        let table = include_str!("./sql/create_table_test.sql");
+        let _ = client.query(table, &[]).await;
+    }
+    let client_ref = Arc::new(client);
+
+    let mut tasks = vec![];
+    for _i in 0..2 {
+        let client = client_ref.clone();
+
+        tasks.push(tokio::spawn(async move {
+            let text = include_str!("./sql/insert_into_table_test.sql");
+            let _ = client
+                .query(
+                    text,
+                    &[
+                        &"joe",
+                        &"doe",
+                        &"9",
+                        &"88 Colin P Kelly Jr St, San Francisco, CA 94107, United States",
+                        &"joe.doe@example.com",
+                    ],
+                )
+                .await;
+        }));
+    }
+    tasks.push(tokio::spawn(async move {
+        //assert that db can't be dropped because is being accessed by other users
+        drop_db(&mut config, "db_test", NoTls, |result| {
+            assert!(result.is_err());
+            if let Err(e) = result {
+                assert_eq!(e.code, "55006");
+                eprintln!("Error dropping db {:?}", e);
+            }
+        })
+        .await;
+        //force drop db
+        forcedrop_db(&mut config, "db_test", NoTls, Confirm::force(), |result| {
+            assert!(result.is_ok());
+        })
+        .await
+    }));
+    for handle in tasks {
+        let _ = handle.await;
+    }
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn table_exists_test() {
+    let db_name = "pglitdb";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    //the `table_name` argument should not be empty
+    let tb_exist = table_exists(&client, "", "student").await;
+    assert!(!tb_exist);
+
+    let table = include_str!("./sql/create_table_test.sql");
+    let _ = client.query(table, &[]).await;
+    assert!(table_exists(&client, "", "student").await);
+    assert!(table_exists(&client, "public", "student").await);
+    // the names are bound as parameters and never interpolated into the statement
+    assert!(!table_exists(&client, "", "student' OR ''='").await);
+    assert!(!table_exists(&client, "$schema_name", "student").await);
+}
+
+#[tokio::test]
+async fn try_table_exists_closed_client_test() {
+    let db_name = "pglit_try_table_exists";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    // dropping the connection closes the client
+    drop(connection);
+
+    let err = try_table_exists(&client, "", "student").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Connection);
+    assert!(!table_exists(&client, "", "student").await);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn table_row_estimate_test() {
+    let db_name = "pglit_row_estimate";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    // the table doesn't exist yet
+    assert!(table_row_estimate(&client, "", "student", false)
+        .await
+        .is_err());
+
+    let table = include_str!("./sql/create_table_test.sql");
+    let text = include_str!("./sql/insert_into_table_test.sql");
+    let _ = client.query(table, &[]).await;
+    let _ = client
+        .query(
+            text,
+            &[
+                &"joe",
+                &"doe",
+                &"9",
+                &"88 Colin P Kelly Jr St, San Francisco, CA 94107, United States",
+                &"joe.doe@example.com",
+            ],
+        )
+        .await;
+    let estimate = table_row_estimate(&client, "", "student", true).await;
+    assert_eq!(estimate.unwrap(), 1);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn analyze_table_test() {
+    let db_name = "pglit_analyze_table";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let res = analyze_table(&client, "", "student").await;
+    assert_eq!(res.unwrap_err().code, "42P01");
+
+    let table = include_str!("./sql/create_table_test.sql");
+    let _ = client.query(table, &[]).await;
+    client
+        .batch_execute(
+            "INSERT INTO student(first_name, last_name) SELECT 'joe', 'doe' FROM generate_series(1, 25);",
+        )
+        .await
+        .unwrap();
+    analyze_table(&client, "public", "student").await.unwrap();
+
+    let row = client
+        .query_one(
+            "SELECT reltuples::int8 FROM pg_class WHERE relname = 'student'",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, i64>(0), 25);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn row_exists_test() {
+    let db_name = "pglit_row_exists";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let table = include_str!("./sql/create_table_test.sql");
+    let text = include_str!("./sql/insert_into_table_test.sql");
+    let _ = client.query(table, &[]).await;
+    assert!(!row_exists(&client, "", "student", "", &[]).await.unwrap());
+
+    let _ = client
+        .query(
+            text,
+            &[
+                &"joe",
+                &"doe",
+                &"9",
+                &"88 Colin P Kelly Jr St, San Francisco, CA 94107, United States",
+                &"joe.doe@example.com",
+            ],
+        )
+        .await;
+    assert!(row_exists(&client, "", "student", "", &[]).await.unwrap());
+    assert!(row_exists(
+        &client,
+        "public",
+        "student",
+        "email = $1 AND last_name = $2",
+        &[&"joe.doe@example.com", &"doe"]
+    )
+    .await
+    .unwrap());
+    assert!(
+        !row_exists(&client, "", "student", "email = $1", &[&"jane@example.com"])
+            .await
+            .unwrap()
+    );
+    let res = row_exists(&client, "", "pglit_missing_table", "", &[]).await;
+    assert_eq!(res.unwrap_err().code, "42P01");
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn custom_error_from_pg_error_test() {
+    let db_name = "pglit_custom_error";
+    let config = get_tokio_config();
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+
+    let pg_error = client
+        .query("SELECT * FROM pglit_missing_table", &[])
+        .await
+        .unwrap_err();
+    let e = CustomError::from(pg_error);
+    assert_eq!(e.code, "42P01");
+    assert_eq!(e.message, "relation pglit_missing_table does not exist");
+
+    // `?` converts the errors of direct tokio_postgres calls
+    async fn count(client: &tokio_postgres::Client) -> Result<i64, CustomError> {
+        let row = client
+            .query_one("SELECT count(*) FROM pglit_missing_table", &[])
+            .await?;
+        Ok(row.get(0))
+    }
+    assert_eq!(count(&client).await.unwrap_err().code, "42P01");
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn qualified_table_exists_test() {
+    let db_name = "pglit_qualified_table";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client
+        .batch_execute(
+            r#"CREATE SCHEMA reporting; CREATE TABLE reporting.orders(id INT); CREATE SCHEMA "my.schema"; CREATE TABLE "my.schema"."Orders"(id INT); CREATE TABLE items(id INT);"#,
+        )
+        .await
+        .unwrap();
+
+    assert!(qualified_table_exists(&client, "reporting.orders")
+        .await
+        .unwrap());
+    assert!(qualified_table_exists(&client, "Reporting.ORDERS")
+        .await
+        .unwrap());
+    assert!(qualified_table_exists(&client, "items").await.unwrap());
+    assert!(qualified_table_exists(&client, r#""my.schema"."Orders""#)
+        .await
+        .unwrap());
+    assert!(!qualified_table_exists(&client, r#""my.schema".Orders"#)
+        .await
+        .unwrap());
+    assert!(!qualified_table_exists(&client, "my.schema.Orders")
+        .await
+        .unwrap());
+    assert!(!qualified_table_exists(&client, "reporting.items")
+        .await
+        .unwrap());
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn vacuum_table_test() {
+    let db_name = "pglit_vacuum_table";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let table = include_str!("./sql/create_table_test.sql");
+    let _ = client.query(table, &[]).await;
+
+    vacuum_table(&client, "", "student", &VacuumOptions::default())
+        .await
+        .unwrap();
+
+    let options = VacuumOptions {
+        full: true,
+        analyze: true,
+        full_size_limit: Some(1),
+    };
+    match vacuum_table(&client, "public", "student", &options).await {
+        Err(VacuumError::TableTooLarge { size, limit }) => {
+            assert!(size > 1);
+            assert_eq!(limit, 1);
+        }
+        result => panic!("expected VACUUM FULL to be refused, got {:?}", result),
+    }
+    let options = VacuumOptions {
+        full_size_limit: None,
+        ..options
+    };
+    vacuum_table(&client, "public", "student", &options)
+        .await
+        .unwrap();
+
+    match vacuum_table(&client, "", "pglit_missing_table", &options).await {
+        Err(VacuumError::Postgres(e)) => assert_eq!(e.code, "42P01"),
+        result => panic!("expected the table to be missing, got {:?}", result),
+    }
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn create_db_events_test() {
+    let db_name = "pglit_create_db_events";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(16);
+    create_db_with_events(
+        &mut config,
+        db_name,
+        NoTls,
+        &CreateDbOptions::default(),
+        &sender,
+        |result| result.unwrap(),
+    )
+    .await;
+    drop_db_with_events(&mut config, db_name, NoTls, false, &sender, |result| {
+        result.unwrap()
+    })
+    .await;
+    drop_db_with_events(&mut config, db_name, NoTls, false, &sender, |result| {
+        assert!(result.is_err())
+    })
+    .await;
+    drop(sender);
+
+    let mut events = vec![];
+    while let Some(event) = receiver.recv().await {
+        events.push(event);
+    }
+    assert_eq!(events.len(), 12);
+    assert_eq!(
+        events[0],
+        PglitEvent::Started {
+            action: "CREATE",
+            db_name: db_name.to_string()
+        }
+    );
+    assert_eq!(
+        events[1],
+        PglitEvent::AdminConnected {
+            admin_db: "postgres".to_string()
+        }
+    );
+    assert_eq!(
+        events[2],
+        PglitEvent::StatementSent {
+            statement: format!("CREATE DATABASE {};", db_name)
+        }
+    );
+    assert_eq!(events[3], PglitEvent::Completed { rows: 0 });
+    assert_eq!(
+        events[4],
+        PglitEvent::Started {
+            action: "DROP",
+            db_name: db_name.to_string()
+        }
+    );
+    match &events[11] {
+        PglitEvent::Failed { code, .. } => assert_eq!(code, "3D000"),
+        event => panic!("expected a failed event, got {:?}", event),
+    }
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn admin_target_test() {
+    let db_name = "pglit_admin_target";
+    let admin_config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut admin_config.clone(), db_name).await;
+
+    // the target points at a closed port of another address, only the admin target is reachable
+    let mut config = tkconfig::new();
+    let _ = config
+        .host("localhost")
+        .port(1)
+        .user(admin_config.get_user().unwrap())
+        .password(admin_config.get_password().unwrap());
+    let admin_target = AdminTarget {
+        host: Some("127.0.0.1".to_string()),
+        port: Some(admin_config.get_ports()[0]),
+    };
+
+    let options = CreateDbOptions {
+        admin_target: Some(admin_target.clone()),
+        ..CreateDbOptions::default()
+    };
+    create_db_with_options(&mut config, db_name, NoTls, &options, |result| {
+        result.unwrap()
+    })
+    .await;
+    assert_eq!(config.get_dbname(), Some(db_name));
+    assert_eq!(config.get_ports(), &[1]);
+    assert!(config.connect(NoTls).await.is_err());
+
+    let mut target_config = admin_config.clone();
+    let _ = target_config.dbname(db_name);
+    assert!(target_config.connect(NoTls).await.is_ok());
+
+    let options = DropDbOptions {
+        force: true,
+        admin_target: Some(admin_target),
+        ..DropDbOptions::default()
+    };
+    drop_db_with_options(&mut config, db_name, NoTls, &options, |result| {
+        result.unwrap()
+    })
+    .await;
+    drop_db(&mut config, db_name, NoTls, |result| {
+        assert!(result.is_err())
+    })
+    .await;
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn list_schemas_test() {
+    let db_name = "pglit_list_schemas";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client
+        .batch_execute("CREATE SCHEMA zeta; CREATE SCHEMA alpha;")
+        .await
+        .unwrap();
+
+    let schemas = list_schemas(&client, false).await.unwrap();
+    assert_eq!(schemas, vec!["alpha", "public", "zeta"]);
+
+    let schemas = list_schemas(&client, true).await.unwrap();
+    assert!(schemas.contains(&"pg_catalog".to_string()));
+    assert!(schemas.contains(&"information_schema".to_string()));
+    assert!(schemas.contains(&"pg_toast".to_string()));
+    let mut sorted = schemas.clone();
+    sorted.sort();
+    assert_eq!(schemas, sorted);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn insufficient_privilege_test() {
+    let db_name = "pglit_insufficient_privilege";
+    let admin_config = get_tokio_config();
+    let (client, connection) = admin_config.connect(NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client
+        .batch_execute(
+            "DROP ROLE IF EXISTS pglit_unprivileged; \
+             CREATE ROLE pglit_unprivileged LOGIN NOCREATEDB PASSWORD 'unprivileged';",
+        )
+        .await
+        .unwrap();
+
+    let mut config = admin_config.clone();
+    let _ = config.user("pglit_unprivileged").password("unprivileged");
+    create_db(&mut config, db_name, NoTls, |result| {
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InsufficientPrivilege);
+        assert!(error.message.contains("`CREATEDB` privilege"));
+    })
+    .await;
+
+    // nothing listens on this port
+    let mut config = tkconfig::new();
+    let _ = config.host("127.0.0.1").port(1).user("pglit_unprivileged");
+    create_db(&mut config, db_name, NoTls, |result| {
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::Connection);
+    })
+    .await;
+
+    client
+        .batch_execute("DROP ROLE pglit_unprivileged;")
+        .await
+        .unwrap();
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn apply_spec_test() {
+    let db_name = "pglit_apply_spec";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let user = config.get_user().unwrap().to_string();
+    let spec = DatabaseSpec {
+        schemas: vec![
+            SchemaSpec {
+                name: "public".to_string(),
+                owner: None,
+            },
+            SchemaSpec {
+                name: "billing".to_string(),
+                owner: Some(user),
+            },
+        ],
+        extensions: vec!["plpgsql".to_string(), "citext".to_string()],
+    };
+
+    let outcome = apply_spec(&client, &spec).await.unwrap();
+    assert_eq!(
+        outcome,
+        SpecOutcome {
+            created_schemas: vec!["billing".to_string()],
+            skipped_schemas: vec!["public".to_string()],
+            created_extensions: vec!["citext".to_string()],
+            skipped_extensions: vec!["plpgsql".to_string()],
+        }
+    );
+    assert!(list_schemas(&client, false)
+        .await
+        .unwrap()
+        .contains(&"billing".to_string()));
+
+    let outcome = apply_spec(&client, &spec).await.unwrap();
+    assert!(outcome.created_schemas.is_empty());
+    assert!(outcome.created_extensions.is_empty());
+    assert_eq!(outcome.skipped_schemas, vec!["public", "billing"]);
+    assert_eq!(outcome.skipped_extensions, vec!["plpgsql", "citext"]);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn verify_pool_test() {
+    let db_name = "pglit_verify_pool";
+    let mut config = get_deadpool_config();
+    config.dbname = Some(db_name.to_string());
+
+    let pool = deadpool_create_db(config.clone(), Some(Runtime::Tokio1), NoTls)
+        .await
+        .unwrap();
+    assert!(verify_pool(&pool).await.is_ok());
+
+    pool.close();
+    match verify_pool(&pool).await {
+        Err(e) => assert_eq!(e.code, "pool"),
+        result => panic!("expected the pool to be closed, got {:?}", result),
+    }
+
+    // nothing listens on this port
+    config.port = Some(1);
+    let pool = config.create_pool(Some(Runtime::Tokio1), NoTls).unwrap();
+    match verify_pool(&pool).await {
+        Err(e) => assert_eq!(e.kind(), ErrorKind::Connection),
+        result => panic!("expected the database to be unreachable, got {:?}", result),
+    }
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn rename_table_test() {
+    let db_name = "pglit_rename_table";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client
+        .batch_execute("CREATE SCHEMA archive; CREATE TABLE archive.orders (id int);")
+        .await
+        .unwrap();
+    let table = include_str!("./sql/create_table_test.sql");
+    let _ = client.query(table, &[]).await;
+
+    rename_table(&client, "", "student", "pupil").await.unwrap();
+    assert!(!table_exists(&client, "public", "student").await);
+    assert!(table_exists(&client, "public", "pupil").await);
+
+    rename_table(&client, "archive", "orders", "old_orders")
+        .await
+        .unwrap();
+    assert!(table_exists(&client, "archive", "old_orders").await);
+
+    let e = rename_table(&client, "", "student", "pupil")
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, "42P01");
+}
+
+// locks the exact DDL generated for the actions passed by the public functions
+#[tokio::test]
+async fn generated_db_statements_test() {
+    let db_name = "pglit_generated_statements";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(32);
+    let options = CreateDbOptions::default();
+    create_db_with_events(&mut config, db_name, NoTls, &options, &sender, |result| {
+        result.unwrap()
+    })
+    .await;
+    drop_db_with_events(&mut config, db_name, NoTls, false, &sender, |result| {
+        result.unwrap()
+    })
+    .await;
+    create_db_with_events(&mut config, db_name, NoTls, &options, &sender, |result| {
+        result.unwrap()
+    })
+    .await;
+    drop_db_with_events(&mut config, db_name, NoTls, true, &sender, |result| {
+        result.unwrap()
+    })
+    .await;
+    drop(sender);
+
+    let mut statements = vec![];
+    while let Some(event) = receiver.recv().await {
+        if let PglitEvent::StatementSent { statement } = event {
+            statements.push(statement);
+        }
+    }
+    let expected = if cfg!(feature = "quotes") {
+        vec![
+            r#"CREATE DATABASE "pglit_generated_statements";"#,
+            r#"DROP DATABASE "pglit_generated_statements";"#,
+            r#"CREATE DATABASE "pglit_generated_statements";"#,
+            r#"DROP DATABASE "pglit_generated_statements" WITH (FORCE);"#,
+        ]
+    } else {
+        vec![
+            "CREATE DATABASE pglit_generated_statements;",
+            "DROP DATABASE pglit_generated_statements;",
+            "CREATE DATABASE pglit_generated_statements;",
+            "DROP DATABASE pglit_generated_statements WITH (FORCE);",
+        ]
+    };
+    assert_eq!(statements, expected);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn sequences_test() {
+    let db_name = "pglit_sequences";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client
+        .batch_execute(
+            "CREATE TABLE orders (id serial PRIMARY KEY, label text); \
+             CREATE SEQUENCE invoice_number; \
+             INSERT INTO orders (label) VALUES ('a'), ('b');",
+        )
+        .await
+        .unwrap();
+
+    let sequences = list_sequences(&client, "").await.unwrap();
+    assert_eq!(sequences, vec!["invoice_number", "orders_id_seq"]);
+    assert!(list_sequences(&client, "pg_catalog")
+        .await
+        .unwrap()
+        .is_empty());
+
+    reset_sequence(&client, "public", "orders_id_seq", 100)
+        .await
+        .unwrap();
+    let row = client
+        .query_one("INSERT INTO orders (label) VALUES ('c') RETURNING id", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, i32>(0), 100);
+    let row = client
+        .query_one("SELECT count(*) FROM orders", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, i64>(0), 3);
+
+    let e = reset_sequence(&client, "", "pglit_missing_sequence", 1)
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, "42P01");
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn pglit_reuses_admin_session_test() {
+    let db_name = "pglit_admin_session_reuse";
+    let application_name = "pglit_admin_session_reuse";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+    let _ = config.application_name(application_name);
+
+    let (client, connection) = connect(get_tokio_config(), "postgres", NoTls)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let sessions = "SELECT count(DISTINCT pid) FROM pg_stat_activity WHERE application_name = $1";
+
+    let pglit = PgLit::new(config, NoTls);
+    for _ in 0..3 {
+        pglit.create_db(db_name, |res| assert!(res.is_ok())).await;
+        pglit
+            .create_db(db_name, |res| assert_eq!(res.unwrap_err().code, "42P04"))
+            .await;
+        pglit.drop_db(db_name, |res| assert!(res.is_ok())).await;
+        pglit
+            .forcedrop_db(db_name, Confirm::force(), |res| {
+                assert_eq!(res.unwrap_err().code, "3D000")
+            })
+            .await;
+    }
+    let row = client
+        .query_one(sessions, &[&application_name])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, i64>(0), 1);
+
+    // a closed admin connection is replaced
+    client
+        .query_one(
+            "SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE application_name = $1",
+            &[&application_name],
+        )
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    pglit.create_db(db_name, |res| assert!(res.is_ok())).await;
+    pglit.drop_db(db_name, |res| assert!(res.is_ok())).await;
+
+    // the admin connection is released once the session is closed
+    pglit.close_admin().await;
+    let row = client
+        .query_one(sessions, &[&application_name])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, i64>(0), 0);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn table_privileges_test() {
+    let db_name = "pglit_table_privileges";
+    let role_name = "pglit_privileges_reader";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+    create_db(&mut config.clone(), db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    ensure_app_role(&config, db_name, role_name, "secret", NoTls)
+        .await
+        .unwrap();
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client
+        .batch_execute("CREATE SCHEMA reports; CREATE TABLE reports.daily (id int);")
+        .await
+        .unwrap();
+    assert!(table_privileges(&client, "reports", "daily", role_name)
+        .await
+        .unwrap()
+        .is_empty());
+
+    client
+        .batch_execute(&format!(
+            "GRANT UPDATE, SELECT ON reports.daily TO {}",
+            role_name
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        table_privileges(&client, "reports", "daily", role_name)
+            .await
+            .unwrap(),
+        vec!["SELECT", "UPDATE"]
+    );
+    // the role name is matched the way the server folds an unquoted name
+    assert_eq!(
+        table_privileges(&client, "reports", "daily", "PGLIT_Privileges_Reader")
+            .await
+            .unwrap(),
+        vec!["SELECT", "UPDATE"]
+    );
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn with_admin_client_test() {
+    let db_name = "pglit_with_admin_client";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (current_database, pids) = with_admin_client(&config, NoTls, |client| {
+        Box::pin(async move {
+            let row = client
+                .query_one("SELECT current_database()::text, pg_backend_pid()", &[])
+                .await
+                .unwrap();
+            client
+                .batch_execute("CREATE DATABASE pglit_with_admin_client")
+                .await
+                .unwrap();
+            let other = client
+                .query_one("SELECT pg_backend_pid()", &[])
+                .await
+                .unwrap();
+            (
+                row.get::<_, String>(0),
+                (row.get::<_, i32>(1), other.get::<_, i32>(0)),
+            )
+        })
+    })
+    .await
+    .unwrap();
+    assert_eq!(current_database, "postgres");
+    assert_eq!(pids.0, pids.1);
+
+    drop_db(&mut config, db_name, NoTls, |res| assert!(res.is_ok())).await;
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn verify_after_create_test() {
+    // unquoted names are folded to lower case by the server
+    let db_name = "PgLit_Verify_Create";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let options = CreateDbOptions {
+        verify_after_create: true,
+        ..CreateDbOptions::default()
+    };
+    create_db_with_options(&mut config.clone(), db_name, NoTls, &options, |res| {
+        assert_eq!(res.unwrap(), 0);
+    })
+    .await;
+    create_db_with_options(&mut config.clone(), db_name, NoTls, &options, |res| {
+        assert_eq!(res.unwrap_err().code, "42P04");
+    })
+    .await;
+    let mut target_config = config.clone();
+    let _ = target_config.dbname("pglit_verify_create");
+    assert!(target_config.connect(NoTls).await.is_ok());
+    drop_db(&mut config, db_name, NoTls, |res| assert!(res.is_ok())).await;
+}
+
+#[tokio::test]
+async fn error_statement_test() {
+    let db_name = "pglit_error_statement";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let expected = if cfg!(feature = "quotes") {
+        r#"DROP DATABASE "pglit_error_statement";"#
+    } else {
+        "DROP DATABASE pglit_error_statement;"
+    };
+    drop_db(&mut config.clone(), db_name, NoTls, |res| {
+        let error = res.unwrap_err();
+        assert_eq!(error.code, "3D000");
+        assert_eq!(error.statement.as_deref(), Some(expected));
+    })
+    .await;
+
+    // errors of statements not generated by pglit don't carry one
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let error = CustomError::from(client.batch_execute("SELEC 1").await.unwrap_err());
+    assert_eq!(error.statement, None);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn create_schemas_in_test() {
+    let db_names = ["pglit_schemas_in_a", "pglit_schemas_in_b"];
+    let mut config = get_tokio_config();
+    for db_name in db_names {
+        //reset test if run more than once
+        let _ = reset_test(&mut config, db_name).await;
+        create_db(&mut config.clone(), db_name, NoTls, |res| {
+            assert!(res.is_ok());
+        })
+        .await;
+    }
+    let target = |db_name: &str, schemas_names: Vec<&str>| {
+        let mut config = get_tokio_config();
+        let _ = config.dbname(db_name);
+        let schemas_names = schemas_names.into_iter().map(String::from).collect();
+        (config, schemas_names)
+    };
+    let targets = vec![
+        target(db_names[0], vec!["tenant_a", "shared"]),
+        target("pglit_schemas_in_missing", vec!["tenant_c"]),
+        target(db_names[1], vec!["tenant_b"]),
+    ];
+
+    let results = create_schemas_in(targets, NoTls, 2).await;
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert_eq!(results[1].as_ref().unwrap_err().code, "3D000");
+    assert!(results[2].is_ok());
+
+    for (db_name, expected) in [
+        (db_names[0], vec!["public", "shared", "tenant_a"]),
+        (db_names[1], vec!["public", "tenant_b"]),
+    ] {
+        let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("connection error: {}", e);
+            }
+        });
+        assert_eq!(list_schemas(&client, false).await.unwrap(), expected);
+    }
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn alter_schema_owner_test() {
+    let db_name = "pglit_alter_schema_owner";
+    let role_name = "pglit_schema_owner";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+    create_db(&mut config.clone(), db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    ensure_app_role(&config, db_name, role_name, "secret", NoTls)
+        .await
+        .unwrap();
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client.batch_execute("CREATE SCHEMA tenant").await.unwrap();
+    let owner = "SELECT nspowner::regrole::text FROM pg_namespace WHERE nspname = 'tenant'";
+
+    alter_schema_owner(&client, "tenant", role_name)
+        .await
+        .unwrap();
+    let row = client.query_one(owner, &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), role_name);
+
+    let e = alter_schema_owner(&client, "tenant", "pglit_missing_role")
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, "42704");
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn connect_from_deadpool_config_test() {
+    let db_name = "pglit_connect_from_deadpool";
+    //reset test if run more than once
+    let _ = reset_test(&mut get_tokio_config(), db_name).await;
+    let mut config = get_deadpool_config();
+    config.dbname = Some(db_name.to_string());
+
+    let (client, connection) = connect_from_deadpool_config(&config, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let row = client
+        .query_one("SELECT current_database()::text", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, String>(0), db_name);
+
+    config.dbname = None;
+    match connect_from_deadpool_config(&config, NoTls).await {
+        Err(ConnectConfigError::Config(ConfigError::DbnameEmpty)) => {}
+        result => panic!("expected a missing dbname, got {:?}", result.err()),
+    }
+}
+
+#[tokio::test]
+async fn create_db_if_missing_test() {
+    let db_name = "pglit_create_db_if_missing";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    drop_db(&mut config, db_name, NoTls, |_| ()).await;
+
+    assert!(create_db_if_missing(&mut config, db_name, NoTls)
+        .await
+        .unwrap());
+    assert!(!create_db_if_missing(&mut config, db_name, NoTls)
+        .await
+        .unwrap());
+
+    let mut config = get_tokio_config();
+    let _ = config.password("wrong_password");
+    let e = create_db_if_missing(&mut config, db_name, NoTls)
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, "28P01");
+}
+
+#[tokio::test]
+async fn tables_exist_test() {
+    let db_name = "pglit_tables_exist";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client
+        .batch_execute("CREATE TABLE users(id INT); CREATE SCHEMA billing; CREATE TABLE billing.invoices(id INT);")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        tables_exist(&client, "", &["users", "invoices", "orders"])
+            .await
+            .unwrap(),
+        vec![true, false, false]
+    );
+    assert_eq!(
+        tables_exist(&client, "billing", &["users", "invoices"])
+            .await
+            .unwrap(),
+        vec![false, true]
+    );
+    assert!(tables_exist(&client, "", &[]).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "compares timings, run it with `cargo test tables_exist_timing_test -- --ignored --nocapture`"]
+async fn tables_exist_timing_test() {
+    let db_name = "pglit_tables_exist_timing";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let names: Vec<String> = (0..50).map(|i| format!("table_{}", i)).collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    for name in &names[..25] {
+        client
+            .batch_execute(&format!("CREATE TABLE {}(id INT);", name))
+            .await
+            .unwrap();
+    }
+    // warm up the connection and the plan cache of the server
+    let _ = tables_exist(&client, "", &names).await.unwrap();
+    let _ = table_exists(&client, "", names[0]).await;
+
+    let start = std::time::Instant::now();
+    let mut looped = Vec::with_capacity(names.len());
+    for name in &names {
+        looped.push(table_exists(&client, "", name).await);
+    }
+    let loop_elapsed = start.elapsed();
+
+    let start = std::time::Instant::now();
+    let batched = tables_exist(&client, "", &names).await.unwrap();
+    let batch_elapsed = start.elapsed();
+
+    println!(
+        "checking {} tables: {:?} with table_exists in a loop, {:?} with tables_exist",
+        names.len(),
+        loop_elapsed,
+        batch_elapsed
+    );
+    assert_eq!(looped, batched);
+    assert!(batch_elapsed < loop_elapsed);
+}
+
+#[tokio::test]
+async fn drop_db_reports_connections_test() {
+    let db_name = "pglit_drop_connections";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let mut blocker_config = get_tokio_config();
+    let _ = blocker_config.application_name("pglit_blocker");
+    let (client, connection) = connect(blocker_config, db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let pid: i32 = client
+        .query_one("SELECT pg_backend_pid()", &[])
+        .await
+        .unwrap()
+        .get(0);
+
+    let e = drop_db(&mut config, db_name, NoTls, |result| result)
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, "55006");
+    let blocker = e
+        .connections
+        .iter()
+        .find(|c| c.pid == pid)
+        .expect("the open session should be listed");
+    assert_eq!(blocker.application_name.as_deref(), Some("pglit_blocker"));
+    assert!(blocker.user.is_some());
+    assert!(e.message.contains(&pid.to_string()));
+
+    forcedrop_db(&mut config, db_name, NoTls, Confirm::force(), |result| {
+        assert!(result.is_ok());
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn apply_migrations_test() {
+    let db_name = "pglit_apply_migrations";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (mut client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let migrations = [
+        ("0001", "CREATE TABLE users (id BIGSERIAL PRIMARY KEY);"),
+        (
+            "0002",
+            "ALTER TABLE users ADD COLUMN email TEXT; CREATE INDEX users_email ON users (email);",
+        ),
+    ];
+    assert_eq!(
+        apply_migrations(&mut client, &migrations).await.unwrap(),
+        vec!["0001".to_string(), "0002".to_string()]
+    );
+    // re-running is a no-op
+    assert!(apply_migrations(&mut client, &migrations)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // a failing migration is rolled back and stops the run
+    let broken = [
+        migrations[0],
+        migrations[1],
+        (
+            "0003",
+            "CREATE TABLE orders (id INT); SELECT * FROM missing;",
+        ),
+        ("0004", "CREATE TABLE invoices (id INT);"),
+    ];
+    match apply_migrations(&mut client, &broken).await {
+        Err(MigrationError::Failed { version, error }) => {
+            assert_eq!(version, "0003");
+            assert_eq!(error.code, "42P01");
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert_eq!(
+        tables_exist(&client, "", &["orders", "invoices"])
+            .await
+            .unwrap(),
+        vec![false, false]
+    );
+    let versions: Vec<String> = client
+        .query("SELECT version FROM pglit_migrations ORDER BY version", &[])
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+    assert_eq!(versions, vec!["0001".to_string(), "0002".to_string()]);
+}
+
+#[tokio::test]
+async fn connect_managed_test() {
+    let db_name = "pglit_connect_managed";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let mut client = connect_managed(config.clone(), db_name, NoTls)
+        .await
+        .unwrap();
+    let pid: i32 = client
+        .query_one("SELECT pg_backend_pid()", &[])
+        .await
+        .unwrap()
+        .get(0);
+    // `DerefMut` gives access to the methods taking `&mut Client`
+    let transaction = client.transaction().await.unwrap();
+    transaction.commit().await.unwrap();
+
+    // dropping the client aborts the connection task, which closes the connection
+    drop(client);
+    let (observer, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let mut closed = false;
+    for _ in 0..50 {
+        let rows = observer
+            .query("SELECT 1 FROM pg_stat_activity WHERE pid = $1", &[&pid])
+            .await
+            .unwrap();
+        if rows.is_empty() {
+            closed = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(closed);
+}
+
+#[tokio::test]
+async fn create_db_if_not_exists_test() {
+    let db_name = "pglit_create_db_if_not_exists";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    drop_db(&mut config, db_name, NoTls, |_| ()).await;
+
+    for _ in 0..2 {
+        create_db_if_not_exists(&mut config, db_name, NoTls, |result| match result {
+            Ok(_n) => (),
+            Err(e) => panic!("unexpected error {:?}", e),
+        })
+        .await;
+    }
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn create_db_from_template_test() {
+    let template = "pglit_template_src";
+    let db_name = "pglit_template_copy";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    drop_db(&mut config, db_name, NoTls, |_| ()).await;
+    let _ = reset_test(&mut config, template).await;
+
+    let (client, connection) = connect(config.clone(), template, NoTls).await.unwrap();
+    let connection = tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client
+        .batch_execute("CREATE TABLE seeded(id INT)")
+        .await
+        .unwrap();
+
+    let options = CreateDbOptions {
+        template: Some(template.to_string()),
+        encoding: Some("UTF8".to_string()),
+        ..CreateDbOptions::default()
+    };
+    // the template can't be copied while a session is connected to it
+    create_db_with_options(&mut config.clone(), db_name, NoTls, &options, |res| {
+        assert_eq!(res.unwrap_err().code, "55006");
+    })
+    .await;
+
+    drop(client);
+    let _ = connection.await;
+    create_db_with_options(&mut config.clone(), db_name, NoTls, &options, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    assert!(table_exists(&client, "", "seeded").await);
+}
+
+#[tokio::test]
+async fn database_exists_test() {
+    let db_name = "pglit_Database_Exists";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    drop_db(&mut config, db_name, NoTls, |_| ()).await;
+    assert!(!database_exists(&mut config, db_name, NoTls).await);
+
+    create_db(&mut config, db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    assert!(database_exists(&mut config, db_name, NoTls).await);
+    assert!(database_exists(&mut config, "postgres", NoTls).await);
+
+    let _ = config.password("wrong_password");
+    assert!(!database_exists(&mut config, "postgres", NoTls).await);
+}
+
+#[tokio::test]
+async fn drop_db_if_exists_test() {
+    let db_name = "pglit_never_created";
+    let mut config = get_tokio_config();
+
+    drop_db_if_exists(&mut config, db_name, NoTls, |res| {
+        assert_eq!(res.unwrap(), 0);
+    })
+    .await;
+
+    create_db(&mut config, db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    drop_db_if_exists(&mut config, db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    assert!(!database_exists(&mut config, db_name, NoTls).await);
+}
+
+#[tokio::test]
+async fn try_create_and_drop_db_test() -> Result<(), CustomError> {
+    let db_name = "pglit_try_variants";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+
+    let _ = try_create_db(&mut config, db_name, NoTls).await?;
+    let e = try_create_db(&mut config, db_name, NoTls)
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, "42P04");
+    let _ = try_drop_db(&mut config, db_name, NoTls).await?;
+    assert_eq!(
+        try_drop_db(&mut config, db_name, NoTls)
+            .await
+            .unwrap_err()
+            .code,
+        "3D000"
+    );
+
+    let _ = try_create_db(&mut config, db_name, NoTls).await?;
+    let (_client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let _ = try_forcedrop_db(&mut config, db_name, NoTls, Confirm::force()).await?;
+    assert!(!database_exists(&mut config, db_name, NoTls).await);
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_helpers_close_sessions_test() {
+    let mut config = get_tokio_config();
+    let _ = config.application_name("pglit_admin_helpers_close");
+
+    for _ in 0..20 {
+        assert!(database_exists(&mut config, "postgres", NoTls).await);
+        let _ = connection_limit(&config, "postgres", NoTls).await.unwrap();
+        let _ = can_create_databases(&config, NoTls).await.unwrap();
+        // a failed statement closes its session too
+        assert!(
+            run_admin_query(&config, "SELECT * FROM pglit_missing", &[], NoTls)
+                .await
+                .is_err()
+        );
+    }
+
+    // every admin connection is shut down before the call returns
+    let rows = run_admin_query(
+        &get_tokio_config(),
+        "SELECT 1 FROM pg_stat_activity WHERE application_name = 'pglit_admin_helpers_close'",
+        &[],
+        NoTls,
+    )
+    .await
+    .unwrap();
+    assert!(rows.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "creates and drops 1000 databases, run it with `cargo test -- --ignored`"]
+async fn create_drop_loop_closes_connections_test() {
+    let db_name = "pglit_create_drop_loop";
+    let mut config = get_tokio_config();
+    let _ = config.application_name("pglit_create_drop_loop");
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+
+    for _ in 0..1000 {
+        try_create_db(&mut config, db_name, NoTls).await.unwrap();
+        try_drop_db(&mut config, db_name, NoTls).await.unwrap();
+    }
+
+    // every admin connection is shut down before the call returns
+    let rows = run_admin_query(
+        &get_tokio_config(),
+        "SELECT 1 FROM pg_stat_activity WHERE application_name = 'pglit_create_drop_loop'",
+        &[],
+        NoTls,
+    )
+    .await
+    .unwrap();
+    assert!(rows.is_empty());
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn create_db_timeout_test() {
+    let template = "pglit_timeout_template";
+    let db_name = "pglit_timeout_copy";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+    reset_test(&mut config, template).await;
+
+    // a server that accepts the connection but never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut sockets = vec![];
+        while let Ok((socket, _addr)) = listener.accept().await {
+            sockets.push(socket);
+        }
+    });
+    let mut silent_config = tkconfig::new();
+    let _ = silent_config.user("pglit").host("127.0.0.1").port(port);
+    let options = CreateDbOptions {
+        timeout: Some(Duration::from_millis(200)),
+        ..CreateDbOptions::default()
+    };
+    let e = create_db_with_options(&mut silent_config, db_name, NoTls, &options, |res| res)
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, "timeout");
+    assert_eq!(e.kind(), ErrorKind::Timeout);
+    assert!(e.pg_error.is_none());
+
+    // copying a template waits for the sessions connected to it to end
+    let (_client, connection) = connect(config.clone(), template, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let options = CreateDbOptions {
+        template: Some(template.to_string()),
+        timeout: Some(Duration::from_secs(1)),
+        ..CreateDbOptions::default()
+    };
+    let e = create_db_with_options(&mut config.clone(), db_name, NoTls, &options, |res| res)
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, "timeout");
+    assert!(e.message.contains("running the statement"));
+    assert!(!database_exists(&mut config, db_name, NoTls).await);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn pglit_schemas_and_tables_test() {
+    let db_name = "pglit_client_schemas";
+    let pglit = PgLit::new(get_tokio_config(), NoTls);
+    pglit.drop_db(db_name, |_res| {}).await;
+
+    // the database isn't created by the checks
+    assert_eq!(
+        pglit
+            .table_exists(db_name, "", "student")
+            .await
+            .unwrap_err()
+            .code,
+        "3D000"
+    );
+    pglit
+        .create_db(db_name, |res| {
+            assert!(res.is_ok());
+        })
+        .await;
+    pglit
+        .create_schemas(db_name, &["tenant_a", "tenant_b"])
+        .await
+        .unwrap();
+    let (client, connection) = pglit.connect(db_name).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client
+        .batch_execute("CREATE TABLE tenant_a.student(id INT)")
+        .await
+        .unwrap();
+    assert!(pglit
+        .table_exists(db_name, "tenant_a", "student")
+        .await
+        .unwrap());
+    assert!(!pglit
+        .table_exists(db_name, "tenant_b", "student")
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn schema_exists_test() {
+    let db_name = "pglit_schema_exists";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+
+    let client = connect_managed(config.clone(), db_name, NoTls)
+        .await
+        .unwrap();
+    assert!(schema_exists(&client, "public").await);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    assert!(!schema_exists(&client, &format!("pglit_missing_{}", nanos)).await);
+
+    create_schemas(&client, &["Tenant"], false, |res| res.unwrap()).await;
+    assert!(schema_exists(&client, "Tenant").await);
+
+    // a closed connection is reported as a missing schema instead of panicking
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    drop(connection);
+    assert!(!schema_exists(&client, "public").await);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn drop_schemas_test() {
+    let db_name = "pglit_drop_schemas";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+
+    let client = connect_managed(config, db_name, NoTls).await.unwrap();
+    create_schemas(&client, &["empty_schm", "full_schm"], false, |res| {
+        res.unwrap()
+    })
+    .await;
+    client
+        .batch_execute("CREATE TABLE full_schm.items (id INT);")
+        .await
+        .unwrap();
+
+    let err = drop_schemas(&client, &["full_schm"], false, |res| res.unwrap_err()).await;
+    assert_eq!(err.code, "2BP01");
+    assert!(schema_exists(&client, "full_schm").await);
+
+    drop_schemas(
+        &client,
+        &["empty_schm", "", "full_schm", "public"],
+        true,
+        |res| res.unwrap(),
+    )
+    .await;
+    assert!(!schema_exists(&client, "empty_schm").await);
+    assert!(!schema_exists(&client, "full_schm").await);
+    assert!(!schema_exists(&client, "public").await);
+
+    let err = drop_schemas(&client, &["public"], false, |res| res.unwrap_err()).await;
+    assert_eq!(err.code, "3F000");
+}
+
+#[tokio::test]
+async fn rename_db_test() {
+    let old_name = "pglit_rename_old";
+    let new_name = "pglit_rename_new";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, old_name).await;
+    drop_db_if_exists(&mut config, new_name, NoTls, |res| res.unwrap()).await;
+
+    let (client, connection) = connect(config.clone(), old_name, NoTls).await.unwrap();
+    let connection = tokio::spawn(connection);
+    let err = rename_db(&config, old_name, new_name, NoTls, |res| res.unwrap_err()).await;
+    assert_eq!(err.code, "55006");
+    drop(client);
+    let _ = connection.await;
+
+    rename_db(&config, old_name, new_name, NoTls, |res| res.unwrap()).await;
+    assert!(!database_exists(&mut config, old_name, NoTls).await);
+    assert!(database_exists(&mut config, new_name, NoTls).await);
+    drop_db(&mut config, new_name, NoTls, |res| res.unwrap()).await;
+}
+
+#[tokio::test]
+async fn clone_db_test() {
+    let source_db = "pglit_clone_seed";
+    let new_db = "pglit_clone_copy";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, new_db).await;
+    reset_test(&mut config, source_db).await;
+
+    let (client, connection) = connect(config.clone(), source_db, NoTls).await.unwrap();
+    let connection = tokio::spawn(connection);
+    client
+        .batch_execute("CREATE TABLE seeded(id INT); INSERT INTO seeded VALUES (1);")
+        .await
+        .unwrap();
+    // the source database can't be copied while a session is connected to it
+    let err = clone_db(&mut config, source_db, new_db, NoTls, |res| {
+        res.unwrap_err()
+    })
+    .await;
+    assert_eq!(err.code, "55006");
+    drop(client);
+    let _ = connection.await;
+
+    clone_db(&mut config, source_db, new_db, NoTls, |res| res.unwrap()).await;
+    let client = connect_managed(config, new_db, NoTls).await.unwrap();
+    assert!(table_exists(&client, "", "seeded").await);
+    let row = client
+        .query_one("SELECT id FROM seeded", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, i32>(0), 1);
+}
+
+#[tokio::test]
+async fn create_table_test() {
+    let db_name = "pglit_create_table";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+
+    let client = connect_managed(config, db_name, NoTls).await.unwrap();
+    let columns = [
+        ColumnDef {
+            primary_key: true,
+            ..ColumnDef::new("id", "INT")
+        },
+        ColumnDef {
+            nullable: false,
+            ..ColumnDef::new("title", "VARCHAR(50)")
+        },
+        ColumnDef {
+            nullable: false,
+            default: Some("3".to_string()),
+            ..ColumnDef::new("credits", "SMALLINT")
+        },
+    ];
+    create_table(&client, "", "course", &columns, |res| res.unwrap()).await;
+    assert!(table_exists(&client, "public", "course").await);
+
+    client
+        .batch_execute("INSERT INTO course (id, title) VALUES (1, 'algebra')")
+        .await
+        .unwrap();
+    let row = client
+        .query_one("SELECT credits FROM course WHERE id = 1", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, i16>(0), 3);
+    let err = client
+        .batch_execute("INSERT INTO course (id) VALUES (2)")
+        .await
+        .unwrap_err();
+    assert_eq!(err.code().map(|code| code.code()), Some("23502"));
+    let err = client
+        .batch_execute("INSERT INTO course (id, title) VALUES (1, 'geometry')")
+        .await
+        .unwrap_err();
+    assert_eq!(err.code().map(|code| code.code()), Some("23505"));
+
+    let err = create_table(&client, "", "course", &columns, |res| res.unwrap_err()).await;
+    assert_eq!(err.code, "42P07");
+}
+
+#[tokio::test]
+async fn drop_table_test() {
+    let db_name = "pglit_drop_table";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+
+    let client = connect_managed(config, db_name, NoTls).await.unwrap();
+    let err = drop_table(&client, "", "missing", false, false, |res| res.unwrap_err()).await;
+    assert_eq!(err.code, "42P01");
+    drop_table(&client, "", "missing", true, false, |res| res.unwrap()).await;
+
+    create_table(
+        &client,
+        "",
+        "parent",
+        &[ColumnDef::new("id", "INT")],
+        |res| res.unwrap(),
+    )
+    .await;
+    client
+        .batch_execute("CREATE VIEW parent_view AS SELECT id FROM parent")
+        .await
+        .unwrap();
+    let err = drop_table(&client, "", "parent", false, false, |res| res.unwrap_err()).await;
+    assert_eq!(err.code, "2BP01");
+    assert!(table_exists(&client, "", "parent").await);
+
+    drop_table(&client, "public", "parent", true, true, |res| res.unwrap()).await;
+    assert!(!table_exists(&client, "", "parent").await);
+}
+
+#[tokio::test]
+async fn column_exists_test() {
+    let db_name = "pglit_column_exists";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+
+    let client = connect_managed(config, db_name, NoTls).await.unwrap();
+    assert!(!column_exists(&client, "", "account", "id").await);
+    create_table(
+        &client,
+        "",
+        "account",
+        &[ColumnDef::new("id", "INT")],
+        |res| res.unwrap(),
+    )
+    .await;
+    assert!(column_exists(&client, "", "account", "id").await);
+    assert!(column_exists(&client, "public", "account", "id").await);
+    assert!(!column_exists(&client, "", "account", "email").await);
+
+    if !column_exists(&client, "", "account", "email").await {
+        client
+            .batch_execute("ALTER TABLE account ADD COLUMN email TEXT")
+            .await
+            .unwrap();
+    }
+    assert!(column_exists(&client, "", "account", "email").await);
+}
+
+async fn existing_databases(config: &tkconfig, names: &[&str]) -> Vec<String> {
+    let statement =
+        "SELECT datname::text FROM pg_database WHERE datname = ANY($1) ORDER BY datname COLLATE \"C\"";
+    run_admin_query(config, statement, &[&names], NoTls)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get(0))
+        .collect()
+}
+
+#[tokio::test]
+async fn identifier_mode_test() {
+    let quoted_name = "Pglit_Mode_Quoted";
+    let raw_name = "Pglit_Mode_Raw";
+    let mut config = get_tokio_config();
+    let quoted = DropDbOptions {
+        identifier_mode: IdentifierMode::Quoted,
+        ..DropDbOptions::default()
+    };
+    let raw = DropDbOptions {
+        identifier_mode: IdentifierMode::Raw,
+        ..DropDbOptions::default()
+    };
+    //reset test if run more than once
+    drop_db_with_options(&mut config, quoted_name, NoTls, &quoted, |_| ()).await;
+    drop_db_with_options(&mut config, raw_name, NoTls, &raw, |_| ()).await;
+
+    let options = CreateDbOptions {
+        identifier_mode: IdentifierMode::Quoted,
+        ..CreateDbOptions::default()
+    };
+    create_db_with_options(&mut config, quoted_name, NoTls, &options, |res| {
+        res.unwrap()
+    })
+    .await;
+    let options = CreateDbOptions {
+        identifier_mode: IdentifierMode::Raw,
+        ..CreateDbOptions::default()
+    };
+    create_db_with_options(&mut config, raw_name, NoTls, &options, |res| res.unwrap()).await;
+
+    // the quoted name keeps its case while the raw name is folded to lower case
+    let names = [quoted_name, "pglit_mode_raw"];
+    assert_eq!(
+        existing_databases(&config, &names).await,
+        vec![quoted_name.to_string(), "pglit_mode_raw".to_string()]
+    );
+
+    drop_db_with_options(&mut config, quoted_name, NoTls, &quoted, |res| res.unwrap()).await;
+    drop_db_with_options(&mut config, raw_name, NoTls, &raw, |res| res.unwrap()).await;
+    assert!(existing_databases(&config, &names).await.is_empty());
+}
+
+#[tokio::test]
+async fn embedded_double_quote_test() {
+    let db_name = r#"pglit_my"db"#;
+    let mut config = get_tokio_config();
+    let drop_options = DropDbOptions {
+        identifier_mode: IdentifierMode::Quoted,
+        ..DropDbOptions::default()
+    };
+    //reset test if run more than once
+    drop_db_with_options(&mut config, db_name, NoTls, &drop_options, |_| ()).await;
+
+    let options = CreateDbOptions {
+        identifier_mode: IdentifierMode::Quoted,
+        ..CreateDbOptions::default()
+    };
+    create_db_with_options(&mut config, db_name, NoTls, &options, |res| res.unwrap()).await;
+    // the embedded quote is kept instead of being stripped to `pglit_mydb`
+    assert_eq!(
+        existing_databases(&config, &[db_name, "pglit_mydb"]).await,
+        vec![db_name.to_string()]
+    );
+
+    drop_db_with_options(&mut config, db_name, NoTls, &drop_options, |res| {
+        res.unwrap()
+    })
+    .await;
+    assert!(existing_databases(&config, &[db_name]).await.is_empty());
+}
+
+#[tokio::test]
+async fn list_databases_test() {
+    let db_name = "pglit_list_databases";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+    create_db(&mut config, db_name, NoTls, |res| res.unwrap()).await;
+
+    let databases = list_databases(&config, false, NoTls).await.unwrap();
+    let database = databases.iter().find(|db| db.name == db_name).unwrap();
+    assert_eq!(database.owner, "pglit");
+    assert_eq!(database.encoding, "UTF8");
+    assert!(!database.is_template);
+    assert!(!databases
+        .iter()
+        .any(|db| db.name == "postgres" || db.is_template));
+    let mut names: Vec<&str> = databases.iter().map(|db| db.name.as_str()).collect();
+    names.sort_unstable();
+    assert_eq!(
+        names,
+        databases
+            .iter()
+            .map(|db| db.name.as_str())
+            .collect::<Vec<&str>>()
+    );
+
+    let databases = list_databases(&config, true, NoTls).await.unwrap();
+    assert!(databases.iter().any(|db| db.name == "postgres"));
+    assert!(databases
+        .iter()
+        .any(|db| db.name == "template1" && db.is_template));
+    assert!(databases.iter().any(|db| db.name == db_name));
+}
+
+#[tokio::test]
+async fn grant_privileges_test() {
+    let db_name = "pglit_grants";
+    let role_name = "pglit_grantee";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+    let admin = connect_managed(config.clone(), "postgres", NoTls)
+        .await
+        .unwrap();
+    admin
+        .batch_execute("DROP ROLE IF EXISTS pglit_grantee; CREATE ROLE pglit_grantee;")
+        .await
+        .unwrap();
+    create_db(&mut config, db_name, NoTls, |res| res.unwrap()).await;
+    // `PUBLIC` can connect to a new database by default
+    admin
+        .batch_execute("REVOKE ALL ON DATABASE pglit_grants FROM PUBLIC;")
+        .await
+        .unwrap();
+
+    let has_privilege = |privilege: &'static str| {
+        let admin = &admin;
+        async move {
+            admin
+                .query_one(
+                    "SELECT has_database_privilege($1, $2, $3)",
+                    &[&role_name, &db_name, &privilege],
+                )
+                .await
+                .unwrap()
+                .get::<_, bool>(0)
+        }
+    };
+    let mut spec = GrantSpec {
+        privileges: vec!["CONNECT".to_string()],
+        object: GrantObject::Database(db_name.to_string()),
+        grantee: role_name.to_string(),
+    };
+    assert!(!has_privilege("CONNECT").await);
+    grant_privileges(&admin, &spec, |res| res.unwrap()).await;
+    assert!(has_privilege("CONNECT").await);
+    assert!(!has_privilege("CREATE").await);
+
+    revoke_privileges(&admin, &spec, |res| res.unwrap()).await;
+    assert!(!has_privilege("CONNECT").await);
+
+    grant_all_on_database(&config, db_name, role_name, NoTls, |res| res.unwrap()).await;
+    assert!(has_privilege("CONNECT").await);
+    assert!(has_privilege("CREATE").await);
+    assert!(has_privilege("TEMPORARY").await);
+
+    let client = connect_managed(config, db_name, NoTls).await.unwrap();
+    spec.object = GrantObject::Table {
+        schema_name: String::new(),
+        table_name: "missing".to_string(),
+    };
+    spec.privileges = vec!["SELECT".to_string(), "INSERT".to_string()];
+    let err = grant_privileges(&client, &spec, |res| res.unwrap_err()).await;
+    assert_eq!(err.code, "42P01");
+
+    spec.object = GrantObject::Schema("public".to_string());
+    spec.privileges = vec!["USAGE".to_string()];
+    spec.grantee = "pglit_missing_role".to_string();
+    let err = grant_privileges(&client, &spec, |res| res.unwrap_err()).await;
+    assert_eq!(err.code, "42704");
+}
+
+#[tokio::test]
+async fn create_extension_test() {
+    let db_name = "pglit_create_extension";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+
+    let client = connect_managed(config, db_name, NoTls).await.unwrap();
+    assert!(extension_exists(&client, "plpgsql").await);
+    assert!(!extension_exists(&client, "pgcrypto").await);
+    create_schemas(&client, &["crypto"], false, |res| res.unwrap()).await;
+    create_extension(&client, "pgcrypto", true, Some("crypto"), |res| {
+        res.unwrap()
+    })
+    .await;
+    let row = client
+        .query_one(
+            "SELECT n.nspname::text FROM pg_extension e JOIN pg_namespace n ON n.oid = e.extnamespace WHERE e.extname = 'pgcrypto'",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, String>(0), "crypto");
+    assert!(extension_exists(&client, "pgcrypto").await);
+
+    create_extension(&client, "pgcrypto", true, None, |res| res.unwrap()).await;
+    let err = create_extension(&client, "pgcrypto", false, None, |res| res.unwrap_err()).await;
+    assert_eq!(err.code, "42710");
+
+    if cfg!(feature = "quotes") {
+        create_extension(&client, "uuid-ossp", true, None, |res| res.unwrap()).await;
+        assert!(extension_exists(&client, "uuid-ossp").await);
+        let row = client
+            .query_one("SELECT uuid_generate_v4()::text", &[])
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, String>(0).len(), 36);
+    }
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn ensure_app_role_test() {
+    let db_name = "pglit_app_role";
+    let role_name = "pglit_app_user";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+    let (client, connection) = connect(config.clone(), "postgres", NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let _ = client
+        .batch_execute("DROP ROLE IF EXISTS pglit_app_user;")
+        .await;
+
+    create_db(&mut config.clone(), db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+
+    // running it twice should be safe
+    for _i in 0..2 {
+        let res = ensure_app_role(&config, db_name, role_name, "app'secret", NoTls).await;
+        assert!(res.is_ok());
+    }
+
+    // the role should be able to connect to the database with its password
+    let mut role_config = config.clone();
+    role_config
+        .user(role_name)
+        .password("app'secret")
+        .dbname(db_name);
+    assert!(role_config.connect(NoTls).await.is_ok());
+}
+
+#[tokio::test]
+async fn ensure_app_role_mixed_case_test() {
+    let db_name = "pglit_app_role_case";
+    let role_name = "Pglit_Case_User";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+    let admin = connect_managed(config.clone(), "postgres", NoTls)
+        .await
+        .unwrap();
+    admin
+        .batch_execute(
+            r#"DROP ROLE IF EXISTS pglit_case_user; DROP ROLE IF EXISTS "Pglit_Case_User";"#,
+        )
+        .await
+        .unwrap();
+    create_db(&mut config, db_name, NoTls, |res| res.unwrap()).await;
+
+    // the second call finds the role created by the first one
+    for _i in 0..2 {
+        ensure_app_role(&config, db_name, role_name, "secret", NoTls)
+            .await
+            .unwrap();
+    }
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn run_admin_query_test() {
+    let config = get_tokio_config();
+    let statement = "SELECT datname FROM pg_database WHERE datname = $1";
+
+    let rows = run_admin_query(&config, statement, &[&"postgres"], NoTls)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, String>(0), "postgres");
+
+    let rows = run_admin_query(&config, statement, &[&"pglit_missing_db"], NoTls)
+        .await
+        .unwrap();
+    assert!(rows.is_empty());
+
+    // malformed statements are returned as errors
+    let res = run_admin_query(&config, "SELEC 1", &[], NoTls).await;
+    if let Err(e) = res {
+        assert_eq!(e.code, "42601");
+    } else {
+        panic!("expected a syntax error");
+    }
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn foreign_keys_test() {
+    let db_name = "pglit_foreign_keys";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let table = include_str!("./sql/create_table_test.sql");
+    let _ = client.query(table, &[]).await;
+    client
+        .batch_execute(
+            "CREATE TABLE enrollment(id BIGSERIAL PRIMARY KEY, student_id BIGINT REFERENCES student(id) ON DELETE CASCADE);",
+        )
+        .await
+        .unwrap();
+
+    assert!(foreign_keys(&client, "", "student")
+        .await
+        .unwrap()
+        .is_empty());
+    let fks = foreign_keys(&client, "public", "enrollment").await.unwrap();
+    assert_eq!(
+        fks,
+        vec![ForeignKeyInfo {
+            constraint_name: "enrollment_student_id_fkey".to_string(),
+            columns: vec!["student_id".to_string()],
+            referenced_schema: "public".to_string(),
+            referenced_table: "student".to_string(),
+            referenced_columns: vec!["id".to_string()],
+            on_delete: "CASCADE".to_string(),
+        }]
+    );
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn cascade_dependents_test() {
+    let db_name = "pglit_cascade_dependents";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client
+        .batch_execute(
+            "CREATE TABLE customers(id BIGSERIAL PRIMARY KEY);
+            CREATE SCHEMA sales;
+            CREATE TABLE sales.orders(id BIGSERIAL PRIMARY KEY, customer_id BIGINT REFERENCES customers(id), parent_id BIGINT REFERENCES sales.orders(id));
+            CREATE TABLE order_items(id BIGSERIAL PRIMARY KEY, order_id BIGINT REFERENCES sales.orders(id));",
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        cascade_dependents(&client, "", "customers").await.unwrap(),
+        vec!["public.order_items".to_string(), "sales.orders".to_string()]
+    );
+    assert_eq!(
+        cascade_dependents(&client, "sales", "orders")
+            .await
+            .unwrap(),
+        vec!["public.order_items".to_string()]
+    );
+    assert!(cascade_dependents(&client, "public", "order_items")
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(cascade_dependents(&client, "public", "missing")
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn list_indexes_test() {
+    let db_name = "pglit_list_indexes";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let table = include_str!("./sql/create_table_test.sql");
+    let _ = client.query(table, &[]).await;
+    client
+        .batch_execute(
+            "CREATE UNIQUE INDEX student_name_idx ON student(first_name, last_name); CREATE INDEX student_email_idx ON student(lower(email));",
+        )
+        .await
+        .unwrap();
+
+    let indexes = list_indexes(&client, "", "student").await.unwrap();
+    assert_eq!(
+        indexes,
+        vec![
+            IndexInfo {
+                name: "student_email_idx".to_string(),
+                is_unique: false,
+                is_primary: false,
+                columns: vec!["lower(email::text)".to_string()],
+            },
+            IndexInfo {
+                name: "student_name_idx".to_string(),
+                is_unique: true,
+                is_primary: false,
+                columns: vec!["first_name".to_string(), "last_name".to_string()],
+            },
+            IndexInfo {
+                name: "student_pkey".to_string(),
+                is_unique: true,
+                is_primary: true,
+                columns: vec!["id".to_string()],
+            },
+        ]
+    );
+    assert!(list_indexes(&client, "public", "pglit_missing_table")
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn readonly_snapshot_test() {
+    let db_name = "pglit_readonly_snapshot";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (mut client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let (writer, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let table = include_str!("./sql/create_table_test.sql");
+    let text = include_str!("./sql/insert_into_table_test.sql");
+    let _ = writer.query(table, &[]).await;
+    let params: [&(dyn tokio_postgres::types::ToSql + Sync); 5] = [
+        &"joe",
+        &"doe",
+        &"9",
+        &"88 Colin P Kelly Jr St, San Francisco, CA 94107, United States",
+        &"joe.doe@example.com",
+    ];
+    let _ = writer.query(text, &params).await;
+
+    let snapshot = begin_readonly_snapshot(&mut client).await.unwrap();
+    let count = "SELECT count(*) FROM student";
+    let before: i64 = snapshot.query_one(count, &[]).await.unwrap().get(0);
+    // rows committed after the snapshot was taken are not visible
+    let _ = writer.query(text, &params).await;
+    let after: i64 = snapshot.query_one(count, &[]).await.unwrap().get(0);
+    assert_eq!(before, 1);
+    assert_eq!(after, 1);
+
+    let res = snapshot.query(text, &params).await;
+    assert_eq!(res.unwrap_err().code().unwrap().code(), "25006");
+}
+
+#[tokio::test]
+async fn create_db_session_user_owner_test() {
+    let db_name = "pglit_session_owned_db";
+    let role_name = "Pglit_Session_Admin";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    reset_test(&mut config, db_name).await;
+    let _ = run_admin_query(
+        &config,
+        r#"DROP ROLE IF EXISTS "Pglit_Session_Admin""#,
+        &[],
+        NoTls,
+    )
+    .await;
+    run_admin_query(
+        &config,
+        r#"CREATE ROLE "Pglit_Session_Admin" LOGIN CREATEDB PASSWORD 'secret'"#,
+        &[],
+        NoTls,
+    )
+    .await
+    .unwrap();
+
+    let mut role_config = config.clone();
+    role_config.user(role_name).password("secret");
+    let options = CreateDbOptions {
+        owner: Some(Owner::SessionUser),
+        identifier_mode: IdentifierMode::Raw,
+        ..CreateDbOptions::default()
+    };
+    // the mixed-case session user isn't folded to lower case in raw mode
+    create_db_with_options(&mut role_config, db_name, NoTls, &options, |res| {
+        res.unwrap()
+    })
+    .await;
+    let rows = run_admin_query(
+        &config,
+        "SELECT pg_get_userbyid(datdba)::text FROM pg_database WHERE datname = $1",
+        &[&db_name],
+        NoTls,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rows[0].get::<_, String>(0), role_name);
+    reset_test(&mut config, db_name).await;
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn create_db_owner_test() {
+    let mut config = get_tokio_config();
+    let db_name = "pglit_owned_db";
+    let role_name = "pglit_owner_role";
+    let statement = "SELECT datdba::regrole::text FROM pg_database WHERE datname = $1";
+    let current_user = config.get_user().unwrap().to_string();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+    let _ = run_admin_query(&config, "DROP ROLE IF EXISTS pglit_owner_role", &[], NoTls).await;
+    let _ = run_admin_query(&config, "CREATE ROLE pglit_owner_role", &[], NoTls).await;
+
+    let owners = vec![
+        (Owner::Named(role_name.to_string()), role_name.to_string()),
+        (Owner::CurrentUser, current_user.clone()),
+        (Owner::SessionUser, current_user),
+    ];
+    for (owner, expected) in owners {
+        let options = CreateDbOptions {
+            owner: Some(owner),
+            ..CreateDbOptions::default()
+        };
+        create_db_with_options(&mut config.clone(), db_name, NoTls, &options, |res| {
+            assert!(res.is_ok());
+        })
+        .await;
+        let rows = run_admin_query(&config, statement, &[&db_name], NoTls)
+            .await
+            .unwrap();
+        assert_eq!(rows[0].get::<_, String>(0), expected);
+        reset_test(&mut config, db_name).await;
+    }
+
+    // a role that doesn't exist is reported by postgres
+    let options = CreateDbOptions {
+        owner: Some(Owner::Named("pglit_missing_role".to_string())),
+        ..CreateDbOptions::default()
+    };
+    create_db_with_options(&mut config.clone(), db_name, NoTls, &options, |res| {
+        if let Err(e) = res {
+            assert_eq!(e.code, "42704");
+        } else {
+            panic!("expected the role to be missing");
+        }
+    })
+    .await;
+    let _ = run_admin_query(&config, "DROP ROLE pglit_owner_role", &[], NoTls).await;
+}
+
+#[tokio::test]
+async fn create_db_with_owner_test() {
+    let mut config = get_tokio_config();
+    let db_name = "pglit_tenant_db";
+    let statement = "SELECT datdba::regrole::text FROM pg_database WHERE datname = $1";
+    //reset test if run more than once
+    drop_db(&mut config, db_name, NoTls, |_| ()).await;
+    let _ = run_admin_query(&config, "DROP ROLE IF EXISTS pglit_tenant_role", &[], NoTls).await;
+    run_admin_query(&config, "CREATE ROLE pglit_tenant_role", &[], NoTls)
+        .await
+        .unwrap();
+
+    create_db_with_owner(&mut config, db_name, "pglit_tenant_role", NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    let rows = run_admin_query(&config, statement, &[&db_name], NoTls)
+        .await
+        .unwrap();
+    assert_eq!(rows[0].get::<_, String>(0), "pglit_tenant_role");
+
+    drop_db(&mut config, db_name, NoTls, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    run_admin_query(&config, "DROP ROLE pglit_tenant_role", &[], NoTls)
+        .await
+        .unwrap();
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn list_extensions_test() {
+    let db_name = "pglit_extensions";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let extensions = list_extensions(&client).await.unwrap();
+    let names = extensions
+        .iter()
+        .map(|e| e.name.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(names, vec!["plpgsql"]);
+    assert!(!extensions[0].version.is_empty());
+}
+
+#[tokio::test]
+async fn update_extension_test() {
+    let db_name = "pglit_update_extension";
+    let config = get_tokio_config();
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    assert!(update_extension(&client, "plpgsql", None).await.is_ok());
+    assert!(update_extension(&client, "plpgsql", Some("1.0"))
+        .await
+        .is_ok());
+
+    if let Err(e) = update_extension(&client, "pglit_missing_extension", None).await {
+        assert_eq!(e.code, "42704");
+    } else {
+        panic!("expected the extension to be missing");
+    }
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn truncate_all_tables_test() {
+    let db_name = "pglit_truncate_all";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    assert_eq!(
+        truncate_all_tables(&client, "", false, false)
+            .await
+            .unwrap(),
+        0
+    );
+
+    let table = include_str!("./sql/create_table_test.sql");
+    let text = include_str!("./sql/insert_into_table_test.sql");
+    let _ = client.query(table, &[]).await;
+    client
+        .batch_execute(
+            r#"CREATE TABLE "Enrollment"(id BIGSERIAL PRIMARY KEY, student_id BIGINT REFERENCES student(id));
+            INSERT INTO "Enrollment"(student_id) VALUES (NULL);"#,
+        )
+        .await
+        .unwrap();
+    let params: [&(dyn tokio_postgres::types::ToSql + Sync); 5] = [
+        &"joe",
+        &"doe",
+        &"9",
+        &"88 Colin P Kelly Jr St, San Francisco, CA 94107, United States",
+        &"joe.doe@example.com",
+    ];
+    let _ = client.query(text, &params).await;
+
+    assert_eq!(
+        truncate_all_tables(&client, "public", true, false)
+            .await
+            .unwrap(),
+        2
+    );
+    let count: i64 = client
+        .query_one("SELECT count(*) FROM student", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(count, 0);
+    // the identity was restarted
+    let rows = client.query(text, &params).await.unwrap();
+    assert_eq!(rows[0].get::<_, i64>("id"), 1);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn slow_queries_test() {
+    let db_name = "pglit_slow_queries";
+    let config = get_tokio_config();
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let (sleeper, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let sleeping = tokio::spawn(async move {
+        let _ = sleeper.query("SELECT pg_sleep(2)", &[]).await;
+    });
+    tokio::time::sleep(Duration::from_millis(800)).await;
+
+    let stats = slow_queries(&client, Duration::from_millis(500))
+        .await
+        .unwrap();
+    let stat = stats
+        .iter()
+        .find(|s| s.query == "SELECT pg_sleep(2)")
+        .expect("the sleeping query should be reported");
+    assert!(stat.pid.is_some());
+    assert!(stat.duration >= Duration::from_millis(500));
+
+    let stats = slow_queries(&client, Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert!(stats.iter().all(|s| s.query != "SELECT pg_sleep(2)"));
+    let _ = sleeping.await;
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn reconcile_db_test() {
+    let db_name = "pglit_reconcile_db";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+
+    let options = CreateDbOptions {
+        connection_limit: Some(5),
+        ..CreateDbOptions::default()
+    };
+    assert!(reconcile_db(&config, db_name, &options, NoTls)
+        .await
+        .unwrap());
+
+    let options = CreateDbOptions {
+        owner: Some(Owner::CurrentUser),
+        encoding: Some("utf8".to_string()),
+        connection_limit: Some(10),
+        ..CreateDbOptions::default()
+    };
+    assert!(!reconcile_db(&config, db_name, &options, NoTls)
+        .await
+        .unwrap());
+    let rows = run_admin_query(
+        &config,
+        "SELECT datconnlimit, pg_encoding_to_char(encoding)::text FROM pg_database WHERE datname = $1",
+        &[&db_name],
+        NoTls,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rows[0].get::<_, i32>(0), 10);
+    assert_eq!(rows[0].get::<_, String>(1), "UTF8");
+
+    let options = CreateDbOptions {
+        encoding: Some("SQL_ASCII".to_string()),
+        ..CreateDbOptions::default()
+    };
+    match reconcile_db(&config, db_name, &options, NoTls).await {
+        Err(ReconcileError::Mismatch {
+            setting, existing, ..
+        }) => {
+            assert_eq!(setting, "encoding");
+            assert_eq!(existing, "UTF8");
+        }
+        result => panic!("expected an encoding mismatch, got {:?}", result),
+    }
+}
+
+#[tokio::test]
+async fn reconcile_db_mixed_case_test() {
+    let db_name = "Pglit_Reconcile_Case";
+    let mut config = get_tokio_config();
+    let drop_options = DropDbOptions {
+        identifier_mode: IdentifierMode::Raw,
+        ..DropDbOptions::default()
+    };
+    //reset test if run more than once
+    drop_db_with_options(&mut config, db_name, NoTls, &drop_options, |_| ()).await;
+
+    let options = CreateDbOptions {
+        identifier_mode: IdentifierMode::Raw,
+        connection_limit: Some(5),
+        ..CreateDbOptions::default()
+    };
+    assert!(reconcile_db(&config, db_name, &options, NoTls)
+        .await
+        .unwrap());
+    // the name was folded to lower case on creation, the second call finds it
+    let options = CreateDbOptions {
+        connection_limit: Some(10),
+        ..options
+    };
+    assert!(!reconcile_db(&config, db_name, &options, NoTls)
+        .await
+        .unwrap());
+}
+
+//#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn create_schema_test() {
+    let db_name = "pglit";
+    let config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config.clone(), db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    create_schemas(&client, &["sport", "", "sales", "books"], true, |_res| {}).await;
+    let table = include_str!("./sql/create_table_test.sql");
+    let _ = client.query(table, &[]).await;
+
+    assert!(table_exists(&client, "sport", "student").await);
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn create_schemas_runtime_names_test() {
+    let db_name = "pglit_runtime_schemas";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let names: Vec<String> = (1..=2).map(|id| format!("tenant_{}", id)).collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    create_schemas(&client, &names, true, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    let schemas = list_schemas(&client, false).await.unwrap();
+    assert!(schemas.contains(&"tenant_1".to_string()));
+    assert!(schemas.contains(&"tenant_2".to_string()));
+    let row = client.query_one("SHOW search_path", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "tenant_1, tenant_2, public");
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn create_schemas_with_search_path_test() {
+    let db_name = "pglit_persistent_search_path";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    create_schemas_with_search_path(
+        &client,
+        &["tenant"],
+        Some(SearchPathScope::Database),
+        None,
+        |res| {
+            assert_eq!(res.unwrap(), Some("tenant".to_string()));
+        },
+    )
+    .await;
+    let row = client.query_one("SHOW search_path", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "tenant, public");
+
+    // a new session of the database uses the persistent search path
+    let (other, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let row = other.query_one("SHOW search_path", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "tenant, public");
+}
+
+#[test]
+fn is_valid_identifier_test() {
+    assert!(is_valid_identifier("tenant_42"));
+    assert!(is_valid_identifier(&"a".repeat(63)));
+    assert!(!is_valid_identifier(""));
+    assert!(!is_valid_identifier(&"a".repeat(64)));
+    assert!(!is_valid_identifier("nul\0byte"));
+
+    if cfg!(feature = "quotes") {
+        assert!(is_valid_identifier("Tenant Name"));
+        assert!(is_valid_identifier("full"));
+        assert_eq!(
+            quote_identifier_checked("Tenant Name"),
+            Some(r#""Tenant Name""#.to_string())
+        );
+    } else {
+        assert!(!is_valid_identifier("Tenant Name"));
+        assert!(!is_valid_identifier("42tenant"));
+        assert!(!is_valid_identifier("tenant;DROP"));
+        assert!(!is_valid_identifier("full"));
+        assert!(!is_valid_identifier("USER"));
+        assert_eq!(
+            quote_identifier_checked("tenant"),
+            Some("tenant".to_string())
+        );
+        assert_eq!(quote_identifier_checked("Tenant Name"), None);
+    }
+}
+
+#[cfg(feature = "quotes")]
+#[tokio::test]
+async fn create_schemas_quoted_search_path_test() {
+    let db_name = "pglit_quoted_search_path";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    create_schemas(&client, &["tenant-1", "Tenant Two"], true, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    let row = client.query_one("SHOW search_path", &[]).await.unwrap();
+    assert_eq!(
+        row.get::<_, String>(0),
+        r#""tenant-1", "Tenant Two", public"#
+    );
+    let row = client
+        .query_one("SELECT current_schema()::text", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, String>(0), "tenant-1");
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn create_schemas_default_schema_test() {
+    let db_name = "pglit_default_schema";
+    let mut config = get_tokio_config();
+    reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    create_schemas_with_search_path(
+        &client,
+        &["sport", "sales", "books"],
+        Some(SearchPathScope::Session),
+        Some("sales"),
+        |res| {
+            assert_eq!(res.unwrap(), Some("sales".to_string()));
+        },
+    )
+    .await;
+    let row = client.query_one("SHOW search_path", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "sales, sport, books, public");
+
+    // the search path is left unchanged without a scope
+    create_schemas_with_search_path(&client, &["music"], None, Some("music"), |res| {
+        assert_eq!(res.unwrap(), None);
+    })
+    .await;
+    let row = client.query_one("SHOW search_path", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "sales, sport, books, public");
+}
+
+#[cfg(feature = "testcontainers")]
+#[tokio::test]
+async fn config_from_host_port_test() {
+    let db_name = "pglit_config_from_host_port";
+    let env_config = get_tokio_config();
+    let port = env_config.get_ports()[0];
+    let password = String::from_utf8_lossy(env_config.get_password().unwrap()).to_string();
+
+    let mut config =
+        pglit::config_from_host_port("127.0.0.1", port, env_config.get_user().unwrap(), &password);
+    create_db(&mut config, db_name, NoTls, |result| result.unwrap()).await;
+    drop_db(&mut config, db_name, NoTls, |result| result.unwrap()).await;
+}
+
+#[test]
+fn redacted_conninfo_test() {
+    let mut config = tkconfig::new();
+    let _ = config
+        .host("db1")
+        .host("db2")
+        .port(5432)
+        .port(5433)
+        .user("app")
+        .password("secret_password")
+        .dbname("appdb");
+    let conninfo = redacted_conninfo(&config);
+    assert_eq!(
+        conninfo,
+        "host=db1,db2 port=5432,5433 user=app password=**** dbname=appdb"
+    );
+    assert!(!conninfo.contains("secret_password"));
+
+    let mut config = tkconfig::new();
+    let _ = config.host("localhost").user("app");
+    assert_eq!(redacted_conninfo(&config), "host=localhost user=app");
+}
+
+#[test]
+fn pglit_debug_redacts_password() {
+    let mut config = tkconfig::new();
+    config
+        .host("127.0.0.1")
+        .user("john_doe")
+        .password("s3cr3t-p4ss")
+        .dbname("testdb");
+    let pglit = PgLit::new(config, NoTls);
+
+    let output = format!("{:?}", pglit);
+    assert!(!output.contains("s3cr3t-p4ss"));
+    assert!(output.contains("****"));
+    assert!(output.contains("john_doe"));
+}
+
+#[cfg(not(feature = "quotes"))]
+#[tokio::test]
+async fn drop_schema_if_empty_test() {
+    let db_name = "pglit_drop_schema";
+    let mut config = get_tokio_config();
+    //reset test if run more than once
+    let _ = reset_test(&mut config, db_name).await;
+
+    let (client, connection) = connect(config.clone(), db_name, NoTls).await.unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    create_schemas(&client, &["empty", "filled"], true, |res| {
+        assert!(res.is_ok());
+    })
+    .await;
+    let table = include_str!("./sql/create_table_test.sql");
+    let _ = client.query(table, &[]).await;
+    assert!(table_exists(&client, "empty", "student").await);
+    let _ = client
+        .batch_execute("ALTER TABLE empty.student SET SCHEMA filled;")
+        .await;
+
+    assert!(drop_schema_if_empty(&client, "empty").await.unwrap());
+    assert!(!drop_schema_if_empty(&client, "filled").await.unwrap());
+    // unquoted names are folded to lower case, like in the `DROP SCHEMA` statement
+    assert!(!drop_schema_if_empty(&client, "FILLED").await.unwrap());
+    assert!(table_exists(&client, "filled", "student").await);
+
+    // dropping a schema that doesn't exist surfaces the postgres error
+    if let Err(e) = drop_schema_if_empty(&client, "empty").await {
+        assert_eq!(e.code, "3F000");
+    } else {
+        panic!("expected the schema to be missing");
+    }
 }
 
 use std::{collections::HashMap, env};